@@ -0,0 +1,30 @@
+//! Runs arbitrary bytes as a ROM for a bounded number of frames. The first
+//! two bytes are held on the keypad so key handling gets exercised too.
+
+#![no_main]
+
+use chip8_emulator::Emulator;
+use libfuzzer_sys::fuzz_target;
+
+const FRAMES: usize = 200;
+const CYCLES_PER_FRAME: usize = 10;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&[low, high], rom)) = data.split_first_chunk() else {
+        return;
+    };
+
+    let mut emulator = Emulator::new();
+    emulator.reset();
+    if emulator.load_rom(rom).is_err() {
+        return;
+    }
+    emulator.keypad.set_state(u16::from_le_bytes([low, high]));
+
+    for _ in 0..FRAMES {
+        for _ in 0..CYCLES_PER_FRAME {
+            emulator.execute_instruction_cycle();
+        }
+        emulator.decrement_timers();
+    }
+});
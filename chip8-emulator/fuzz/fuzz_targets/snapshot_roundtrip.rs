@@ -0,0 +1,42 @@
+//! Runs a ROM while restoring a copy of the emulator from a serde snapshot
+//! at the start of every frame, and checks both end the frame identical.
+//! Catches state a save state forgets.
+
+#![no_main]
+
+use chip8_emulator::Emulator;
+use libfuzzer_sys::fuzz_target;
+
+const FRAMES: usize = 20;
+const CYCLES_PER_FRAME: usize = 10;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&[low, high], rom)) = data.split_first_chunk() else {
+        return;
+    };
+
+    let mut original = Emulator::new();
+    original.reset();
+    if original.load_rom(rom).is_err() {
+        return;
+    }
+    original.keypad.set_state(u16::from_le_bytes([low, high]));
+
+    for frame in 0..FRAMES {
+        let snapshot = serde_json::to_string(&original).unwrap();
+        let mut restored: Emulator = serde_json::from_str(&snapshot).unwrap();
+
+        for _ in 0..CYCLES_PER_FRAME {
+            original.execute_instruction_cycle();
+            restored.execute_instruction_cycle();
+        }
+        original.decrement_timers();
+        restored.decrement_timers();
+
+        assert_eq!(
+            serde_json::to_value(&restored).unwrap(),
+            serde_json::to_value(&original).unwrap(),
+            "diverged in frame {frame}"
+        );
+    }
+});
@@ -0,0 +1,500 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use crate::coverage::Coverage;
+use crate::instruction::Instruction;
+use crate::{Emulator, PROGRAM_START};
+
+/// How control leaves a basic block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    /// Runs into the block starting at `end`, which something else jumps to.
+    Fallthrough,
+    /// `1nnn`.
+    Jump(u16),
+    /// `2nnn`, continuing at `end` once the subroutine returns.
+    Call(u16),
+    /// `00EE`.
+    Return,
+    /// A skip instruction, continuing at `end` or the instruction after it.
+    Skip,
+    /// `Bnnn`, whose target depends on `V0` and isn't followed.
+    ComputedJump(u16),
+    /// An unknown opcode or the end of the ROM.
+    Stop,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: u16,
+    /// The address right after the block's last instruction.
+    pub end: u16,
+    pub exit: Exit,
+}
+
+impl BasicBlock {
+    /// The blocks control can reach within the same subroutine.
+    pub fn successors(&self) -> Vec<u16> {
+        match self.exit {
+            Exit::Fallthrough | Exit::Call(_) => vec![self.end],
+            Exit::Jump(target) => vec![target],
+            Exit::Skip => vec![self.end, self.end.wrapping_add(2)],
+            Exit::Return | Exit::ComputedJump(_) | Exit::Stop => vec![],
+        }
+    }
+}
+
+/// Recursive descent analysis of a ROM: starting from 0x200 it follows
+/// jumps, calls, skips and returns to tell code from data, without running
+/// anything.
+///
+/// Code only reached through `Bnnn` can't be found this way. Seeding the
+/// analysis with a `Coverage` map from an actual run adds every place the
+/// program was seen executing.
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    rom: Vec<u8>,
+    instructions: BTreeMap<u16, Instruction>,
+    blocks: BTreeMap<u16, BasicBlock>,
+    subroutines: BTreeSet<u16>,
+    jump_targets: BTreeSet<u16>,
+    call_graph: BTreeMap<u16, BTreeSet<u16>>,
+}
+
+impl Analysis {
+    pub fn new(rom: &[u8]) -> Self {
+        Self::with_entries(rom, [PROGRAM_START])
+    }
+
+    /// Also starts from every run of bytes the coverage map saw executing.
+    pub fn with_coverage(rom: &[u8], coverage: &Coverage) -> Self {
+        let entries = coverage
+            .ranges()
+            .filter(|(_, access)| access.executed)
+            .map(|(range, _)| *range.start());
+        Self::with_entries(rom, [PROGRAM_START].into_iter().chain(entries))
+    }
+
+    fn with_entries(rom: &[u8], entries: impl IntoIterator<Item = u16>) -> Self {
+        let mut analysis = Self {
+            // Bytes past the end of memory have no address of their own.
+            rom: rom[..rom
+                .len()
+                .min(Emulator::MEMORY_SIZE - PROGRAM_START as usize)]
+                .to_vec(),
+            instructions: BTreeMap::new(),
+            blocks: BTreeMap::new(),
+            subroutines: BTreeSet::new(),
+            jump_targets: BTreeSet::new(),
+            call_graph: BTreeMap::new(),
+        };
+
+        let mut leaders: BTreeSet<u16> = entries.into_iter().collect();
+        walk(
+            &analysis.rom,
+            leaders.clone(),
+            is_chip8,
+            |address, instruction, exit| {
+                analysis.instructions.insert(address, instruction);
+
+                let next = address.wrapping_add(2);
+                match exit {
+                    Some(Exit::Jump(target)) => {
+                        analysis.jump_targets.insert(target);
+                        leaders.insert(target);
+                    }
+                    Some(Exit::Call(target)) => {
+                        analysis.subroutines.insert(target);
+                        leaders.extend([target, next]);
+                    }
+                    Some(Exit::Skip) => leaders.extend([next, next.wrapping_add(2)]),
+                    _ => {}
+                }
+            },
+        );
+
+        for &leader in &leaders {
+            if let Some(block) = analysis.block_at(leader, &leaders) {
+                analysis.blocks.insert(leader, block);
+            }
+        }
+
+        for &entry in analysis.subroutines.iter().chain(&[PROGRAM_START]) {
+            let callees = analysis.callees(entry);
+            analysis.call_graph.insert(entry, callees);
+        }
+
+        analysis
+    }
+
+    fn block_at(&self, start: u16, leaders: &BTreeSet<u16>) -> Option<BasicBlock> {
+        self.instructions.get(&start)?;
+
+        let mut address = start;
+        loop {
+            let Some(instruction) = self.instructions.get(&address) else {
+                // Ran off the end of the ROM.
+                return Some(BasicBlock {
+                    start,
+                    end: address,
+                    exit: Exit::Stop,
+                });
+            };
+
+            let next = address.wrapping_add(2);
+            if let Some(exit) = exit(instruction, is_chip8) {
+                return Some(BasicBlock {
+                    start,
+                    end: next,
+                    exit,
+                });
+            }
+            if leaders.contains(&next) {
+                return Some(BasicBlock {
+                    start,
+                    end: next,
+                    exit: Exit::Fallthrough,
+                });
+            }
+            address = next;
+        }
+    }
+
+    /// Subroutines called from the blocks reachable from `entry` without
+    /// going through another call.
+    fn callees(&self, entry: u16) -> BTreeSet<u16> {
+        let mut callees = BTreeSet::new();
+        let mut visited = BTreeSet::new();
+        let mut pending = vec![entry];
+        while let Some(address) = pending.pop() {
+            if !visited.insert(address) {
+                continue;
+            }
+            let Some(block) = self.blocks.get(&address) else {
+                continue;
+            };
+            if let Exit::Call(target) = block.exit {
+                callees.insert(target);
+            }
+            pending.extend(block.successors());
+        }
+        callees
+    }
+
+    pub fn is_code(&self, address: u16) -> bool {
+        self.instructions.contains_key(&address)
+    }
+
+    pub fn blocks(&self) -> impl Iterator<Item = &BasicBlock> {
+        self.blocks.values()
+    }
+
+    /// Entry points of everything called with `2nnn`.
+    pub fn subroutines(&self) -> &BTreeSet<u16> {
+        &self.subroutines
+    }
+
+    /// The subroutines each subroutine calls, 0x200 standing for the main
+    /// program.
+    pub fn call_graph(&self) -> &BTreeMap<u16, BTreeSet<u16>> {
+        &self.call_graph
+    }
+
+    /// Addresses of `Bnnn` instructions, where the analysis loses track.
+    pub fn computed_jumps(&self) -> impl Iterator<Item = u16> + '_ {
+        self.blocks
+            .values()
+            .filter(|block| matches!(block.exit, Exit::ComputedJump(_)))
+            .map(|block| block.end - 2)
+    }
+
+    /// The name used for `address` in listings, if it gets one.
+    pub fn label(&self, address: u16) -> Option<String> {
+        if address == PROGRAM_START {
+            Some("start".to_owned())
+        } else if self.subroutines.contains(&address) {
+            Some(format!("sub_{address:03X}"))
+        } else if self.jump_targets.contains(&address) {
+            Some(format!("loc_{address:03X}"))
+        } else if self.is_data_target(address) {
+            Some(format!("data_{address:03X}"))
+        } else {
+            None
+        }
+    }
+
+    /// Data that `Annn` or `Bnnn` points at.
+    fn is_data_target(&self, address: u16) -> bool {
+        !self.is_code(address)
+            && self.instructions.values().any(|instruction| {
+                matches!(instruction, Instruction(0xA | 0xB, _, _, _))
+                    && instruction.nnn() == address
+            })
+    }
+
+    /// Writes an assembly listing of the ROM. Code gets a line per
+    /// instruction, everything else a `DB` per byte drawn as a sprite row.
+    pub fn write_listing(&self, mut writer: impl Write) -> io::Result<()> {
+        let end = PROGRAM_START + self.rom.len() as u16;
+        let mut address = PROGRAM_START;
+        while address < end {
+            if let Some(label) = self.label(address) {
+                if address != PROGRAM_START {
+                    writeln!(writer)?;
+                }
+                writeln!(writer, "{label}:")?;
+            }
+
+            let offset = (address - PROGRAM_START) as usize;
+            match self.instructions.get(&address) {
+                Some(instruction) => {
+                    let mut line = String::new();
+                    let _ = instruction.write_mnemonic(&mut line, |target| self.label(target));
+                    if let Instruction(0xB, _, _, _) = instruction {
+                        line = format!("{line:<24}; computed jump");
+                    } else if self.is_code(address + 1) {
+                        line = format!("{line:<24}; overlaps code at {:03X}", address + 1);
+                    }
+                    writeln!(
+                        writer,
+                        "    {address:03X}: {:02X} {:02X}  {line}",
+                        self.rom[offset],
+                        self.rom[offset + 1]
+                    )?;
+                    address += 2;
+                }
+                None => {
+                    let byte = self.rom[offset];
+                    let line = format!("DB #{byte:02X}");
+                    let sprite: String = (0..8)
+                        .map(|bit| if byte & 0x80 >> bit != 0 { '#' } else { '.' })
+                        .collect();
+                    writeln!(
+                        writer,
+                        "    {address:03X}: {byte:02X}     {line:<24}; {sprite}"
+                    )?;
+                    address += 1;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the control flow graph in Graphviz DOT, one node per basic
+    /// block. Calls are dashed and computed jumps red.
+    pub fn write_dot(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "digraph chip8 {{")?;
+        writeln!(writer, "    node [shape=box fontname=monospace];")?;
+
+        for block in self.blocks.values() {
+            let mut label = String::new();
+            if let Some(name) = self.label(block.start) {
+                let _ = write!(label, "{name}:\\l");
+            }
+            let mut address = block.start;
+            while address < block.end {
+                let _ = write!(label, "{address:03X}: ");
+                if let Some(instruction) = self.instructions.get(&address) {
+                    let _ = instruction.write_mnemonic(&mut label, |target| self.label(target));
+                }
+                label.push_str("\\l");
+                address += 2;
+            }
+
+            let color = match block.exit {
+                Exit::ComputedJump(_) => " color=red",
+                _ => "",
+            };
+            writeln!(
+                writer,
+                "    b{:03X} [label=\"{label}\"{color}];",
+                block.start
+            )?;
+
+            for successor in block.successors() {
+                if self.blocks.contains_key(&successor) {
+                    writeln!(writer, "    b{:03X} -> b{successor:03X};", block.start)?;
+                }
+            }
+            if let Exit::Call(target) = block.exit {
+                if self.blocks.contains_key(&target) {
+                    writeln!(
+                        writer,
+                        "    b{:03X} -> b{target:03X} [style=dashed];",
+                        block.start
+                    )?;
+                }
+            }
+        }
+
+        writeln!(writer, "}}")
+    }
+}
+
+/// Follows jumps, calls and skips through `rom` from `entries`, calling
+/// `visit` once for every instruction reached. Opcodes `known` rejects stop
+/// the walk, so the same one serves CHIP-8 and the platforms extending it.
+pub(crate) fn walk(
+    rom: &[u8],
+    entries: impl IntoIterator<Item = u16>,
+    known: impl Fn(&Instruction) -> bool,
+    mut visit: impl FnMut(u16, Instruction, Option<Exit>),
+) {
+    // `F000` is followed by a 16 bit address, which skips jump over.
+    let length = |address| match read(rom, address) {
+        Some(instruction @ Instruction(0xF, 0x0, 0x0, 0x0)) if known(&instruction) => 4,
+        _ => 2,
+    };
+
+    let mut visited = BTreeSet::new();
+    let mut pending: Vec<u16> = entries.into_iter().collect();
+    while let Some(address) = pending.pop() {
+        if !visited.insert(address) {
+            continue;
+        }
+        let Some(instruction) = read(rom, address) else {
+            continue;
+        };
+        let exit = exit(&instruction, &known);
+        visit(address, instruction, exit);
+
+        let next = address.wrapping_add(length(address));
+        match exit {
+            None => pending.push(next),
+            Some(Exit::Jump(target)) => pending.push(target),
+            Some(Exit::Call(target)) => pending.extend([target, next]),
+            Some(Exit::Skip) => pending.extend([next, next.wrapping_add(length(next))]),
+            Some(_) => {}
+        }
+    }
+}
+
+/// The instruction at `address` of a ROM loaded at the program start.
+fn read(rom: &[u8], address: u16) -> Option<Instruction> {
+    let offset = address.checked_sub(PROGRAM_START)? as usize;
+    let bytes = rom.get(offset..offset + 2)?;
+    Some(Instruction(
+        bytes[0] >> 4,
+        bytes[0] & 0xF,
+        bytes[1] >> 4,
+        bytes[1] & 0xF,
+    ))
+}
+
+fn is_chip8(instruction: &Instruction) -> bool {
+    instruction.pattern().is_some()
+}
+
+/// How `instruction` affects control flow, `None` when execution simply
+/// continues with the next instruction.
+fn exit(instruction: &Instruction, known: impl Fn(&Instruction) -> bool) -> Option<Exit> {
+    match instruction {
+        Instruction(0x0, 0x0, 0xE, 0xE) => Some(Exit::Return),
+        Instruction(0x1, _, _, _) => Some(Exit::Jump(instruction.nnn())),
+        Instruction(0x2, _, _, _) => Some(Exit::Call(instruction.nnn())),
+        Instruction(0xB, _, _, _) => Some(Exit::ComputedJump(instruction.nnn())),
+        Instruction(0x3 | 0x4, _, _, _)
+        | Instruction(0x5 | 0x9, _, _, 0x0)
+        | Instruction(0xE, _, 0x9, 0xE)
+        | Instruction(0xE, _, 0xA, 0x1) => Some(Exit::Skip),
+        _ if !known(instruction) => Some(Exit::Stop),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROM: [u8; 20] = [
+        0xA2, 0x12, // 200: LD I, 212
+        0x22, 0x0A, // 202: CALL 20A
+        0x30, 0x00, // 204: SE V0, 0
+        0x12, 0x02, // 206: JP 202
+        0x12, 0x08, // 208: JP 208
+        0xD0, 0x01, // 20A: DRW V0, V0, 1
+        0x22, 0x10, // 20C: CALL 210
+        0x00, 0xEE, // 20E: RET
+        0x00, 0xEE, // 210: RET
+        0xF0, 0x90, // 212: sprite
+    ];
+
+    #[test]
+    fn blocks() {
+        let analysis = Analysis::new(&ROM);
+        let blocks: Vec<_> = analysis
+            .blocks()
+            .map(|block| (block.start, block.end, block.exit))
+            .collect();
+
+        assert_eq!(
+            blocks,
+            [
+                (0x200, 0x202, Exit::Fallthrough),
+                (0x202, 0x204, Exit::Call(0x20A)),
+                (0x204, 0x206, Exit::Skip),
+                (0x206, 0x208, Exit::Jump(0x202)),
+                (0x208, 0x20A, Exit::Jump(0x208)),
+                (0x20A, 0x20E, Exit::Call(0x210)),
+                (0x20E, 0x210, Exit::Return),
+                (0x210, 0x212, Exit::Return),
+            ]
+        );
+        assert!(!analysis.is_code(0x212), "sprite data");
+    }
+
+    #[test]
+    fn call_graph() {
+        let analysis = Analysis::new(&ROM);
+
+        assert_eq!(
+            analysis.call_graph(),
+            &BTreeMap::from([
+                (0x200, BTreeSet::from([0x20A])),
+                (0x20A, BTreeSet::from([0x210])),
+                (0x210, BTreeSet::new()),
+            ])
+        );
+    }
+
+    #[test]
+    fn computed_jumps() {
+        let rom = [
+            0xB2, 0x04, // 200: JP V0, 204
+            0x00, 0x00, // 202
+            0x12, 0x04, // 204: JP 204
+        ];
+
+        let analysis = Analysis::new(&rom);
+        assert_eq!(analysis.computed_jumps().collect::<Vec<_>>(), [0x200]);
+        assert!(!analysis.is_code(0x204), "not followed statically");
+
+        let coverage: Coverage = "200-201 x\n204-205 x\n".parse().unwrap();
+        let analysis = Analysis::with_coverage(&rom, &coverage);
+        assert!(analysis.is_code(0x204), "seen executing");
+    }
+
+    #[test]
+    fn listing() {
+        let mut listing = Vec::new();
+        Analysis::new(&ROM).write_listing(&mut listing).unwrap();
+        let listing = String::from_utf8(listing).unwrap();
+
+        assert!(listing.starts_with("start:\n    200: A2 12  LD I, data_212\n"));
+        assert!(listing.contains("\nloc_202:\n    202: 22 0A  CALL sub_20A\n"));
+        assert!(listing.contains("    212: F0     DB #F0                  ; ####....\n"));
+    }
+
+    #[test]
+    fn dot() {
+        let mut dot = Vec::new();
+        Analysis::new(&ROM).write_dot(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+
+        assert!(dot.starts_with("digraph chip8 {\n"));
+        assert!(dot.contains("    b204 -> b206;\n    b204 -> b208;\n"));
+        assert!(dot.contains("    b202 -> b20A [style=dashed];\n"));
+    }
+}
@@ -0,0 +1,149 @@
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+use crate::display::{indexed_pixels, Display, DISPLAY_HEIGHT, DISPLAY_PIXELS, DISPLAY_WIDTH};
+use crate::palette::Palette;
+
+// GIF delays are in hundredths of a second and most viewers slow anything
+// shorter than 2 down to 10, so frames that would show for less are merged
+// into the next one.
+const MIN_DELAY: u64 = 2;
+const FRAME_RATE: u64 = 60;
+
+/// Writes the display, sampled once per frame, as a looping animated GIF.
+/// Frames that don't change the picture only extend the previous frame's
+/// delay, so idle stretches cost next to nothing.
+pub struct GifRecorder<W: Write> {
+    encoder: gif::Encoder<W>,
+    scale: usize,
+    frame: u64,
+    pending: Option<([bool; DISPLAY_PIXELS], u64)>,
+}
+
+impl<W: Write> GifRecorder<W> {
+    pub fn new(writer: W, scale: u32, palette: Palette) -> io::Result<Self> {
+        let scale = scale.max(1) as usize;
+        let width = (DISPLAY_WIDTH * scale) as u16;
+        let height = (DISPLAY_HEIGHT * scale) as u16;
+
+        let mut encoder =
+            gif::Encoder::new(writer, width, height, &[palette.off, palette.on].concat())
+                .map_err(into_io_error)?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(into_io_error)?;
+
+        Ok(Self {
+            encoder,
+            scale,
+            frame: 0,
+            pending: None,
+        })
+    }
+
+    /// Captures the display at the end of a frame.
+    pub fn record_frame(&mut self, display: &Display) -> io::Result<()> {
+        let now = self.frame * 100 / FRAME_RATE;
+        self.frame += 1;
+
+        let buffer = display.get_buffer();
+        match self.pending {
+            Some((pending, _)) if pending == *buffer => {}
+            Some((_, start)) if now - start < MIN_DELAY => {
+                self.pending = Some((*buffer, start));
+            }
+            _ => {
+                self.write_pending(now)?;
+                self.pending = Some((*buffer, now));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the last frame and the GIF trailer and hands back the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let end = self.frame * 100 / FRAME_RATE;
+        self.write_pending(end)?;
+
+        self.encoder.into_inner()
+    }
+
+    fn write_pending(&mut self, until: u64) -> io::Result<()> {
+        let Some((buffer, start)) = self.pending.take() else {
+            return Ok(());
+        };
+
+        let frame = gif::Frame {
+            width: (DISPLAY_WIDTH * self.scale) as u16,
+            height: (DISPLAY_HEIGHT * self.scale) as u16,
+            delay: (until - start).clamp(MIN_DELAY, u16::MAX as u64) as u16,
+            buffer: Cow::Owned(indexed_pixels(&buffer, self.scale)),
+            ..Default::default()
+        };
+        self.encoder.write_frame(&frame).map_err(into_io_error)
+    }
+}
+
+fn into_io_error(err: gif::EncodingError) -> io::Error {
+    match err {
+        gif::EncodingError::Io(err) => err,
+        err => io::Error::other(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(file: &[u8]) -> Vec<(u16, Vec<u8>)> {
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(file).unwrap();
+
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            frames.push((frame.delay, frame.buffer.to_vec()));
+        }
+        frames
+    }
+
+    #[test]
+    fn deduplicates_frames() {
+        let mut recorder = GifRecorder::new(Vec::new(), 1, Palette::default()).unwrap();
+        let mut display = Display::new();
+
+        for frame in 0..120 {
+            if frame == 60 {
+                display.draw(0, 0, &[0x80]);
+            }
+            recorder.record_frame(&display).unwrap();
+        }
+        let frames = decode(&recorder.finish().unwrap());
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].0, 100, "one second at 60 Hz");
+        assert_eq!(frames[1].0, 100);
+        assert_eq!(frames[0].1[0], 0);
+        assert_eq!(frames[1].1[0], 1);
+    }
+
+    #[test]
+    fn merges_short_frames() {
+        let mut recorder = GifRecorder::new(Vec::new(), 1, Palette::default()).unwrap();
+        let mut display = Display::new();
+
+        for _ in 0..60 {
+            display.draw(0, 0, &[0x80]);
+            recorder.record_frame(&display).unwrap();
+        }
+        let frames = decode(&recorder.finish().unwrap());
+
+        assert!(frames.iter().all(|(delay, _)| *delay >= 2));
+        assert_eq!(
+            frames.iter().map(|(delay, _)| *delay as u32).sum::<u32>(),
+            100,
+            "merging frames keeps the total length"
+        );
+    }
+}
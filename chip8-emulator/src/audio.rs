@@ -0,0 +1,178 @@
+const MAX_TOGGLES: usize = 8;
+
+/// When the buzzer was on during one frame. Besides the state at the start of
+/// the frame it keeps the instruction cycles at which `Fx18` switched the
+/// buzzer on or off, so a beep can start or stop in the middle of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameSound {
+    on_at_start: bool,
+    toggles: [u32; MAX_TOGGLES],
+    toggle_count: usize,
+}
+
+impl FrameSound {
+    pub const fn new(on_at_start: bool) -> Self {
+        Self {
+            on_at_start,
+            toggles: [0; MAX_TOGGLES],
+            toggle_count: 0,
+        }
+    }
+
+    pub(crate) fn toggle(&mut self, cycle: u32) {
+        // Anything past the last slot only flickers within a few cycles, so
+        // the final toggle overwrites it to keep the end state right.
+        let index = self.toggle_count.min(MAX_TOGGLES - 1);
+        self.toggles[index] = cycle;
+        self.toggle_count = if self.toggle_count < MAX_TOGGLES {
+            self.toggle_count + 1
+        } else {
+            self.toggle_count - 1
+        };
+    }
+
+    pub fn is_on_at_start(&self) -> bool {
+        self.on_at_start
+    }
+
+    /// Whether the buzzer was on at instruction `cycle` of the frame.
+    pub fn is_on_at(&self, cycle: u32) -> bool {
+        let toggles = self.toggles[..self.toggle_count]
+            .iter()
+            .filter(|&&toggle| toggle <= cycle)
+            .count();
+
+        self.on_at_start ^ (toggles % 2 == 1)
+    }
+
+    pub fn is_silent(&self) -> bool {
+        !self.on_at_start && self.toggle_count == 0
+    }
+}
+
+/// Renders the buzzer as a square wave at the host's sample rate, one frame
+/// at a time.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AudioSynth {
+    sample_rate: u32,
+    cycles_per_frame: u32,
+    frequency: f32,
+    volume: f32,
+    phase: f32,
+    leftover: u32,
+}
+
+impl AudioSynth {
+    pub const FRAME_RATE: u32 = 60;
+
+    pub fn new(sample_rate: u32, cycles_per_frame: u32) -> Self {
+        Self {
+            sample_rate,
+            cycles_per_frame: cycles_per_frame.max(1),
+            frequency: 440.0,
+            volume: 0.25,
+            phase: 0.0,
+            leftover: 0,
+        }
+    }
+
+    pub fn with_frequency(mut self, frequency: f32) -> Self {
+        self.frequency = frequency;
+        self
+    }
+
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Number of samples the next frame should have. Sample rates that
+    /// aren't a multiple of 60 alternate between lengths so no time is lost.
+    pub fn next_frame_len(&mut self) -> usize {
+        let total = self.sample_rate + self.leftover;
+        self.leftover = total % Self::FRAME_RATE;
+
+        (total / Self::FRAME_RATE) as usize
+    }
+
+    /// Fills `out` with mono samples spanning one frame.
+    pub fn render_frame(&mut self, sound: &FrameSound, out: &mut [f32]) {
+        let phase_step = self.frequency / self.sample_rate as f32;
+        let len = out.len().max(1) as u64;
+
+        for (i, sample) in out.iter_mut().enumerate() {
+            let cycle = (i as u64 * self.cycles_per_frame as u64 / len) as u32;
+
+            *sample = if sound.is_on_at(cycle) {
+                let value = if self.phase < 0.5 {
+                    self.volume
+                } else {
+                    -self.volume
+                };
+
+                self.phase += phase_step;
+                if self.phase >= 1.0 {
+                    self.phase -= 1.0;
+                }
+                value
+            } else {
+                self.phase = 0.0;
+                0.0
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_sound_toggles() {
+        let mut sound = FrameSound::new(false);
+        sound.toggle(3);
+        sound.toggle(7);
+
+        assert!(!sound.is_on_at(2));
+        assert!(sound.is_on_at(3));
+        assert!(sound.is_on_at(6));
+        assert!(!sound.is_on_at(7));
+    }
+
+    #[test]
+    fn frame_sound_overflow_keeps_end_state() {
+        let mut sound = FrameSound::new(false);
+        for cycle in 0..11 {
+            sound.toggle(cycle);
+        }
+
+        assert!(sound.is_on_at(100), "odd number of toggles ends on");
+    }
+
+    #[test]
+    fn frame_len_keeps_time() {
+        let mut synth = AudioSynth::new(22050, 10);
+
+        let total: usize = (0..60).map(|_| synth.next_frame_len()).sum();
+        assert_eq!(total, 22050);
+    }
+
+    #[test]
+    fn renders_part_of_a_frame() {
+        let mut synth = AudioSynth::new(48000, 10);
+        let mut sound = FrameSound::new(false);
+        sound.toggle(5);
+
+        let mut samples = vec![0.0; synth.next_frame_len()];
+        synth.render_frame(&sound, &mut samples);
+
+        assert!(samples[..400].iter().all(|&sample| sample == 0.0));
+        assert!(samples[400..].iter().any(|&sample| sample != 0.0));
+    }
+}
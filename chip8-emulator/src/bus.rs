@@ -0,0 +1,119 @@
+/// Everything the interpreter reads or writes goes through a `Bus`, so
+/// memory can be mirrored, banked, protected or logged without touching the
+/// instruction implementations.
+pub trait Bus {
+    fn read(&self, address: u16) -> u8;
+
+    /// A write made by the running program, e.g. `Fx33` or `Fx55`. Returns
+    /// whether it was accepted, `false` for protected or read only regions.
+    fn write(&mut self, address: u16, value: u8) -> bool;
+
+    /// A write made by the host while setting up the machine, e.g. copying
+    /// the font or the ROM. Implementations that protect regions from the
+    /// program should still accept these.
+    fn load(&mut self, address: u16, data: &[u8]) {
+        for (offset, &value) in data.iter().enumerate() {
+            self.write(address.wrapping_add(offset as u16), value);
+        }
+    }
+
+    /// Zeroes the whole address space.
+    fn clear(&mut self);
+
+    /// Bytes of distinct memory; addresses from here on mirror or are
+    /// unmapped.
+    fn size(&self) -> usize;
+}
+
+/// Flat RAM of `SIZE` bytes, 4kb by default. Addresses past the end mirror
+/// back to the start, and writes below 0x200 can be rejected to keep
+/// programs from overwriting the interpreter area and font.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ram<const SIZE: usize = 0x1000> {
+    #[cfg_attr(feature = "serde", serde(with = "serde_big_array::BigArray"))]
+    memory: [u8; SIZE],
+    protect_interpreter: bool,
+}
+
+impl<const SIZE: usize> Ram<SIZE> {
+    pub const SIZE: usize = SIZE;
+    pub const INTERPRETER_END: u16 = 0x200;
+
+    pub const fn new() -> Self {
+        Self {
+            memory: [0; SIZE],
+            protect_interpreter: false,
+        }
+    }
+
+    pub const fn with_interpreter_protection(mut self, protect: bool) -> Self {
+        self.protect_interpreter = protect;
+        self
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.memory
+    }
+
+    fn index(address: u16) -> usize {
+        address as usize % SIZE
+    }
+}
+
+impl<const SIZE: usize> Bus for Ram<SIZE> {
+    fn read(&self, address: u16) -> u8 {
+        self.memory[Self::index(address)]
+    }
+
+    fn write(&mut self, address: u16, value: u8) -> bool {
+        if self.protect_interpreter && Self::index(address) < Self::INTERPRETER_END as usize {
+            return false;
+        }
+
+        self.memory[Self::index(address)] = value;
+        true
+    }
+
+    fn load(&mut self, address: u16, data: &[u8]) {
+        for (offset, &value) in data.iter().enumerate() {
+            self.memory[Self::index(address.wrapping_add(offset as u16))] = value;
+        }
+    }
+
+    fn clear(&mut self) {
+        self.memory = [0; SIZE];
+    }
+
+    fn size(&self) -> usize {
+        SIZE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mirrors_past_the_end() {
+        let mut ram = Ram::<0x1000>::new();
+
+        ram.write(0x1234, 0xAB);
+
+        assert_eq!(ram.read(0x0234), 0xAB);
+        assert_eq!(ram.read(0x1234), 0xAB);
+    }
+
+    #[test]
+    fn interpreter_protection() {
+        let mut ram = Ram::<0x1000>::new().with_interpreter_protection(true);
+
+        assert!(!ram.write(0x050, 0xAB));
+        assert!(ram.write(0x200, 0xCD));
+        assert_eq!(ram.read(0x050), 0x00, "program writes are rejected");
+        assert_eq!(ram.read(0x200), 0xCD, "program area is writable");
+
+        ram.load(0x050, &[0xAB]);
+        assert_eq!(ram.read(0x050), 0xAB, "host writes are accepted");
+    }
+}
@@ -0,0 +1,221 @@
+use std::io::{self, Read};
+
+use serde::Deserialize;
+
+use crate::database::{KeyMapping, RomInfo};
+use crate::octo::assemble_octo;
+use crate::palette::{parse_color, Palette};
+use crate::quirks::Quirks;
+
+/// An Octo "cartridge": a GIF whose palette indices carry the source and
+/// settings of a program in their two low bits, four pixels to a byte,
+/// most significant bits first. The bytes are a big endian length followed
+/// by that much JSON.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cartridge {
+    /// The Octo source the ROM was assembled from.
+    pub source: String,
+    pub rom: Vec<u8>,
+    /// Instruction cycles per frame.
+    pub tickrate: Option<u32>,
+    pub quirks: Quirks,
+    pub palette: Option<Palette>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Payload {
+    program: String,
+    #[serde(default)]
+    options: Options,
+}
+
+/// The settings Octo saves with a program. Those without a counterpart
+/// here, like `vBlankQuirks`, are ignored.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Options {
+    tickrate: Option<u32>,
+    fill_color: Option<String>,
+    background_color: Option<String>,
+    shift_quirks: bool,
+    load_store_quirks: bool,
+    clip_quirks: bool,
+    jump_quirks: bool,
+    logic_quirks: bool,
+}
+
+impl Cartridge {
+    /// Decodes the payload and assembles the program.
+    pub fn read_from(reader: impl Read) -> io::Result<Self> {
+        let payload = read_payload(reader)?;
+        let payload: Payload = serde_json::from_slice(&payload)?;
+        let rom = assemble_octo(&payload.program)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        let options = payload.options;
+        let palette = match (&options.background_color, &options.fill_color) {
+            (Some(off), Some(on)) => Some(Palette::new(
+                parse_color(off).unwrap_or(Palette::default().off),
+                parse_color(on).unwrap_or(Palette::default().on),
+            )),
+            _ => None,
+        };
+
+        Ok(Self {
+            source: payload.program,
+            rom,
+            tickrate: options.tickrate,
+            // Octo's quirks are named after the newer behaviour.
+            quirks: Quirks {
+                shift_vy: !options.shift_quirks,
+                load_store_increments_i: !options.load_store_quirks,
+                jump_vx: options.jump_quirks,
+                clip_sprites: options.clip_quirks,
+                logic_resets_vf: options.logic_quirks,
+            },
+            palette,
+        })
+    }
+
+    /// The settings as the ROM database would describe them, so frontends
+    /// can treat both the same.
+    pub fn rom_info(&self, title: impl Into<String>) -> RomInfo {
+        RomInfo {
+            title: title.into(),
+            description: None,
+            release: None,
+            authors: Vec::new(),
+            platform: None,
+            quirks: Some(self.quirks),
+            tickrate: self.tickrate,
+            keys: KeyMapping::default(),
+            palette: self.palette,
+        }
+    }
+}
+
+fn read_payload(reader: impl Read) -> io::Result<Vec<u8>> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(reader).map_err(into_io_error)?;
+
+    let mut bytes = Vec::new();
+    let mut byte = 0;
+    let mut pixels = 0;
+    while let Some(frame) = decoder.read_next_frame().map_err(into_io_error)? {
+        for &pixel in frame.buffer.iter() {
+            byte = byte << 2 | pixel & 0x3;
+            pixels += 1;
+            if pixels % 4 == 0 {
+                bytes.push(byte);
+            }
+        }
+    }
+
+    let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+    let (length, payload) = bytes
+        .split_first_chunk::<4>()
+        .ok_or_else(|| invalid("image too small for a cartridge"))?;
+    let length = u32::from_be_bytes(*length) as usize;
+    payload
+        .get(..length)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| invalid("cartridge payload is cut short"))
+}
+
+fn into_io_error(err: gif::DecodingError) -> io::Error {
+    match err {
+        gif::DecodingError::Io(err) => err,
+        err => io::Error::new(io::ErrorKind::InvalidData, err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: u16 = 32;
+
+    /// Writes `json` the way Octo does, over a blank label.
+    fn cartridge(json: &str) -> Vec<u8> {
+        let mut bytes = (json.len() as u32).to_be_bytes().to_vec();
+        bytes.extend(json.as_bytes());
+        encode(&bytes)
+    }
+
+    fn encode(bytes: &[u8]) -> Vec<u8> {
+        let mut pixels: Vec<u8> = bytes
+            .iter()
+            .flat_map(|&byte| [6, 4, 2, 0].map(|shift| 0x4 | byte >> shift & 0x3))
+            .collect();
+        let frame_size = WIDTH as usize * WIDTH as usize;
+        pixels.resize(pixels.len().div_ceil(frame_size) * frame_size, 0x4);
+
+        let palette = [[0x00; 3]; 4]
+            .into_iter()
+            .chain([[0xFF; 3]; 4])
+            .flatten()
+            .collect::<Vec<_>>();
+        let mut gif = Vec::new();
+        let mut encoder = gif::Encoder::new(&mut gif, WIDTH, WIDTH, &palette).unwrap();
+        for pixels in pixels.chunks(frame_size) {
+            let frame = gif::Frame::from_indexed_pixels(WIDTH, WIDTH, pixels.to_vec(), None);
+            encoder.write_frame(&frame).unwrap();
+        }
+        drop(encoder);
+        gif
+    }
+
+    #[test]
+    fn read() {
+        let json = r##"{
+            "program": ": main\n  v0 := 1\n  loop again\n",
+            "options": {
+                "tickrate": 20,
+                "fillColor": "#FFCC00",
+                "backgroundColor": "#996600",
+                "shiftQuirks": true,
+                "loadStoreQuirks": false,
+                "vfOrderQuirks": false,
+                "clipQuirks": true,
+                "jumpQuirks": false,
+                "logicQuirks": true
+            }
+        }"##;
+        // Long enough to need more than one frame.
+        let json = json.to_owned() + &" ".repeat(300);
+
+        let cartridge = Cartridge::read_from(cartridge(&json).as_slice()).unwrap();
+
+        assert_eq!(cartridge.rom, [0x12, 0x02, 0x60, 0x01, 0x12, 0x04]);
+        assert_eq!(cartridge.tickrate, Some(20));
+        assert_eq!(
+            cartridge.quirks,
+            Quirks {
+                shift_vy: false,
+                load_store_increments_i: true,
+                jump_vx: false,
+                clip_sprites: true,
+                logic_resets_vf: true,
+            }
+        );
+        assert_eq!(
+            cartridge.palette,
+            Some(Palette::new([0x99, 0x66, 0x00], [0xFF, 0xCC, 0x00]))
+        );
+        assert_eq!(cartridge.rom_info("Test").quirks, Some(cartridge.quirks));
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Cartridge::read_from(&b"GIF89a"[..]).is_err());
+
+        let cut_short = encode(&[0, 0, 0x10, 0, b'{']);
+        let err = Cartridge::read_from(cut_short.as_slice()).unwrap_err();
+        assert_eq!(err.to_string(), "cartridge payload is cut short");
+
+        let not_assembled = cartridge(r#"{"program": ": start ;"}"#);
+        let err = Cartridge::read_from(not_assembled.as_slice()).unwrap_err();
+        assert_eq!(err.to_string(), "line 1: missing a `main` label");
+    }
+}
@@ -0,0 +1,375 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::bus::Bus;
+use crate::Emulator;
+
+/// What a cheat holds, or where a search looks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CheatTarget {
+    Memory(u16),
+    Register(u8),
+}
+
+impl CheatTarget {
+    pub fn read<B: Bus>(self, emulator: &Emulator<B>) -> u8 {
+        match self {
+            Self::Memory(address) => emulator.memory().read(address),
+            Self::Register(register) => emulator.v_registers()[register as usize & 0xF],
+        }
+    }
+
+    /// Writes like the host does, so protected memory takes it too.
+    pub fn write<B: Bus>(self, emulator: &mut Emulator<B>, value: u8) {
+        match self {
+            Self::Memory(address) => emulator.memory_mut().load(address, &[value]),
+            Self::Register(register) => emulator.v_registers_mut()[register as usize & 0xF] = value,
+        }
+    }
+}
+
+/// `V5` or a hex address like `2F3`.
+impl fmt::Display for CheatTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Memory(address) => write!(f, "{address:03X}"),
+            Self::Register(register) => write!(f, "V{register:X}"),
+        }
+    }
+}
+
+impl FromStr for CheatTarget {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(register) = s.strip_prefix(['V', 'v']) {
+            if register.len() != 1 {
+                return Err(());
+            }
+            return u8::from_str_radix(register, 16)
+                .map(Self::Register)
+                .map_err(|_| ());
+        }
+        u16::from_str_radix(s, 16).map(Self::Memory).map_err(|_| ())
+    }
+}
+
+/// Keeps `target` at `value` while enabled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cheat {
+    pub name: String,
+    pub target: CheatTarget,
+    pub value: u8,
+    pub enabled: bool,
+}
+
+/// A set of named cheats, applied once per frame.
+///
+/// As text, one cheat per line as `<name>: <target> = <value>`, with the
+/// target a register like `VE` or a hex address and the value a hex byte.
+/// `#` starts a comment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cheats {
+    cheats: Vec<Cheat>,
+}
+
+impl Cheats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, cheat: Cheat) {
+        self.cheats.push(cheat);
+    }
+
+    pub fn len(&self) -> usize {
+        self.cheats.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cheats.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cheat> {
+        self.cheats.iter()
+    }
+
+    /// Turns every cheat called `name` on or off, returning whether there
+    /// was one.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        let mut found = false;
+        for cheat in self.cheats.iter_mut().filter(|cheat| cheat.name == name) {
+            cheat.enabled = enabled;
+            found = true;
+        }
+        found
+    }
+
+    /// Writes the values of the enabled cheats, best done before every
+    /// frame's instructions. Cheats for addresses past the end of the bus's
+    /// memory are skipped rather than mirrored onto other bytes.
+    pub fn apply<B: Bus>(&self, emulator: &mut Emulator<B>) {
+        let size = emulator.memory().size();
+        for cheat in self.cheats.iter().filter(|cheat| cheat.enabled) {
+            match cheat.target {
+                CheatTarget::Memory(address) if address as usize >= size => {}
+                target => target.write(emulator, cheat.value),
+            }
+        }
+    }
+}
+
+impl fmt::Display for Cheats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for cheat in &self.cheats {
+            writeln!(f, "{}: {} = {:02X}", cheat.name, cheat.target, cheat.value)?;
+        }
+        Ok(())
+    }
+}
+
+/// A line of a cheat file that isn't `<name>: <target> = <value>`,
+/// counting from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseCheatError {
+    pub line: usize,
+}
+
+impl fmt::Display for ParseCheatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid cheat on line {}", self.line)
+    }
+}
+
+impl FromStr for Cheats {
+    type Err = ParseCheatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut cheats = Cheats::new();
+
+        for (index, line) in s.lines().enumerate() {
+            let error = ParseCheatError { line: index + 1 };
+
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (name, assignment) = line.rsplit_once(':').ok_or(error)?;
+            let (target, value) = assignment.split_once('=').ok_or(error)?;
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(error);
+            }
+
+            cheats.push(Cheat {
+                name: name.to_owned(),
+                target: target.trim().parse().map_err(|_| error)?,
+                value: u8::from_str_radix(value.trim(), 16).map_err(|_| error)?,
+                enabled: true,
+            });
+        }
+
+        Ok(cheats)
+    }
+}
+
+/// How a value has to relate to the previous snapshot to stay a candidate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    /// Is this value now, whatever it was before.
+    Equal(u8),
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+}
+
+impl Comparison {
+    fn matches(self, before: u8, now: u8) -> bool {
+        match self {
+            Self::Equal(value) => now == value,
+            Self::Changed => now != before,
+            Self::Unchanged => now == before,
+            Self::Increased => now > before,
+            Self::Decreased => now < before,
+        }
+    }
+}
+
+/// Narrows memory and the registers down to where a game keeps a number,
+/// like the lives, by comparing snapshots taken as it changes.
+#[derive(Debug, Clone)]
+pub struct CheatSearch {
+    /// Candidates with their value at the last snapshot.
+    candidates: Vec<(CheatTarget, u8)>,
+}
+
+impl CheatSearch {
+    /// Takes the first snapshot, with the registers and all of the bus's
+    /// memory a candidate.
+    pub fn new<B: Bus>(emulator: &Emulator<B>) -> Self {
+        let targets = (0..16)
+            .map(CheatTarget::Register)
+            .chain((0..=u16::MAX).map(CheatTarget::Memory))
+            .take(16 + emulator.memory().size());
+        Self {
+            candidates: targets
+                .map(|target| (target, target.read(emulator)))
+                .collect(),
+        }
+    }
+
+    /// Keeps the candidates whose value now compares to the last snapshot
+    /// as asked, and takes a new one.
+    pub fn filter<B: Bus>(&mut self, emulator: &Emulator<B>, comparison: Comparison) {
+        self.candidates.retain_mut(|(target, value)| {
+            let now = target.read(emulator);
+            let keep = comparison.matches(*value, now);
+            *value = now;
+            keep
+        });
+    }
+
+    /// What's left, with the values at the last snapshot.
+    pub fn candidates(&self) -> &[(CheatTarget, u8)] {
+        &self.candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Ram;
+
+    #[test]
+    fn parse() {
+        let text = "\
+            # BRIX\n\
+            Infinite lives: VE = 05\n\
+            \n\
+            Score: 3A0 = 63 # high enough\n";
+
+        let cheats: Cheats = text.parse().unwrap();
+
+        assert_eq!(
+            cheats.iter().cloned().collect::<Vec<_>>(),
+            [
+                Cheat {
+                    name: "Infinite lives".to_owned(),
+                    target: CheatTarget::Register(0xE),
+                    value: 0x05,
+                    enabled: true,
+                },
+                Cheat {
+                    name: "Score".to_owned(),
+                    target: CheatTarget::Memory(0x3A0),
+                    value: 0x63,
+                    enabled: true,
+                },
+            ]
+        );
+        assert_eq!(
+            cheats.to_string(),
+            "Infinite lives: VE = 05\nScore: 3A0 = 63\n"
+        );
+
+        assert_eq!(
+            "Lives: VG = 05".parse::<Cheats>(),
+            Err(ParseCheatError { line: 1 })
+        );
+        assert_eq!(
+            "Lives: VE = 05\nLives VE 05".parse::<Cheats>(),
+            Err(ParseCheatError { line: 2 })
+        );
+        assert_eq!(
+            "Lives: 10000 = 05".parse::<Cheats>(),
+            Err(ParseCheatError { line: 1 })
+        );
+    }
+
+    #[test]
+    fn apply() {
+        let mut cheats: Cheats = "Lives: V3 = 09\nFlag: 100 = AA\n".parse().unwrap();
+        let mut emulator = Emulator::new();
+
+        cheats.apply(&mut emulator);
+        assert_eq!(emulator.v_registers()[3], 0x09);
+        assert_eq!(emulator.memory().read(0x100), 0xAA);
+
+        assert!(cheats.set_enabled("Lives", false));
+        assert!(!cheats.set_enabled("Speed", false));
+        emulator.v_registers_mut()[3] = 0;
+        cheats.apply(&mut emulator);
+        assert_eq!(emulator.v_registers()[3], 0);
+    }
+
+    #[test]
+    fn apply_past_the_end_of_memory() {
+        let cheats: Cheats = "High: 1FFF = AA
+"
+        .parse()
+        .unwrap();
+
+        let mut emulator = Emulator::new();
+        cheats.apply(&mut emulator);
+        assert_eq!(emulator.memory().read(0x1FFF), 0, "not mirrored into 4K");
+
+        let mut emulator = Emulator::with_bus(Ram::<0x2000>::new());
+        cheats.apply(&mut emulator);
+        assert_eq!(emulator.memory().read(0x1FFF), 0xAA);
+    }
+
+    #[test]
+    fn search() {
+        let mut emulator = Emulator::new();
+        emulator.reset();
+        // Three lives in V4, and a copy in memory.
+        emulator
+            .load_rom(&[
+                0x64, 0x03, // 200: LD V4, 3
+                0xA3, 0x00, // 202: LD I, 300
+                0xF4, 0x55, // 204: LD [I], V0..V4
+                0x74, 0xFF, // 206: ADD V4, -1
+                0xF4, 0x55, // 208: LD [I], V0..V4
+            ])
+            .unwrap();
+        for _ in 0..3 {
+            emulator.execute_instruction_cycle();
+        }
+
+        let mut search = CheatSearch::new(&emulator);
+        search.filter(&emulator, Comparison::Equal(3));
+        assert!(
+            search.candidates().len() > 2,
+            "the rom and font have threes too"
+        );
+
+        emulator.execute_instruction_cycle();
+        emulator.execute_instruction_cycle();
+        search.filter(&emulator, Comparison::Decreased);
+
+        assert_eq!(
+            search.candidates(),
+            [
+                (CheatTarget::Register(4), 2),
+                (CheatTarget::Memory(0x304), 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn search_covers_larger_memory() {
+        let mut emulator = Emulator::with_bus(Ram::<0x2000>::new());
+        emulator.reset();
+
+        let search = CheatSearch::new(&emulator);
+
+        assert_eq!(search.candidates().len(), 16 + 0x2000);
+        assert_eq!(
+            search.candidates().last(),
+            Some(&(CheatTarget::Memory(0x1FFF), 0))
+        );
+        assert_eq!("1FFF".parse(), Ok(CheatTarget::Memory(0x1FFF)));
+    }
+}
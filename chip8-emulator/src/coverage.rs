@@ -0,0 +1,278 @@
+use core::fmt;
+use core::ops::RangeInclusive;
+use core::str::FromStr;
+
+use crate::instruction::Instruction;
+use crate::observer::Observer;
+use crate::Emulator;
+
+/// How the program touched a byte of memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Access {
+    /// Fetched as part of an instruction.
+    pub executed: bool,
+    /// Read as data, by `Dxyn` or `Fx65`.
+    pub read: bool,
+    /// Written by `Fx33` or `Fx55`.
+    pub written: bool,
+}
+
+impl Access {
+    pub const NONE: Self = Self {
+        executed: false,
+        read: false,
+        written: false,
+    };
+
+    pub fn is_none(&self) -> bool {
+        *self == Self::NONE
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (set, flag) in [(self.executed, 'x'), (self.read, 'r'), (self.written, 'w')] {
+            if set {
+                fmt::Write::write_char(f, flag)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An `Observer` marking every byte of memory as executed, read as data or
+/// written, which separates code from sprites and variables in a ROM.
+///
+/// The map is saved with `Display` and loaded with `FromStr` as one line per
+/// run of bytes with the same access, untouched bytes left out:
+///
+/// ```text
+/// 200-245 x
+/// 246-24B r
+/// 300-302 rw
+/// ```
+///
+/// Covers 4K of memory unless given a larger `SIZE` to match the bus;
+/// addresses past the end mirror back to the start the way `Ram` does.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Coverage<const SIZE: usize = { Emulator::MEMORY_SIZE }> {
+    memory: [Access; SIZE],
+}
+
+impl<const SIZE: usize> Coverage<SIZE> {
+    pub const fn new() -> Self {
+        Self {
+            memory: [Access::NONE; SIZE],
+        }
+    }
+
+    pub fn get(&self, address: u16) -> Access {
+        self.memory[address as usize % SIZE]
+    }
+
+    /// Forgets everything, e.g. when a new ROM is loaded.
+    pub fn clear(&mut self) {
+        self.memory = [Access::NONE; SIZE];
+    }
+
+    /// Merges another run's coverage into this one, so several play
+    /// sessions can add up to a more complete map.
+    pub fn merge(&mut self, other: &Self) {
+        for (access, other) in self.memory.iter_mut().zip(&other.memory) {
+            access.executed |= other.executed;
+            access.read |= other.read;
+            access.written |= other.written;
+        }
+    }
+
+    /// Runs of consecutive touched bytes with the same access.
+    pub fn ranges(&self) -> impl Iterator<Item = (RangeInclusive<u16>, Access)> + '_ {
+        let mut address = 0;
+        core::iter::from_fn(move || {
+            while address < SIZE && self.memory[address].is_none() {
+                address += 1;
+            }
+            if address == SIZE {
+                return None;
+            }
+
+            let start = address;
+            let access = self.memory[start];
+            while address < SIZE && self.memory[address] == access {
+                address += 1;
+            }
+            Some((start as u16..=(address - 1) as u16, access))
+        })
+    }
+
+    fn mark(&mut self, address: u16, mark: impl FnOnce(&mut Access)) {
+        mark(&mut self.memory[address as usize % SIZE]);
+    }
+}
+
+impl<const SIZE: usize> Default for Coverage<SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const SIZE: usize> fmt::Debug for Coverage<SIZE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.ranges()).finish()
+    }
+}
+
+impl<const SIZE: usize> Observer for Coverage<SIZE> {
+    fn on_instruction(&mut self, address: u16, _instruction: &Instruction) {
+        self.mark(address, |access| access.executed = true);
+        self.mark(address.wrapping_add(1), |access| access.executed = true);
+    }
+
+    fn on_memory_read(&mut self, address: u16, _value: u8) {
+        self.mark(address, |access| access.read = true);
+    }
+
+    fn on_memory_write(&mut self, address: u16, _value: u8) {
+        self.mark(address, |access| access.written = true);
+    }
+}
+
+impl<const SIZE: usize> fmt::Display for Coverage<SIZE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (range, access) in self.ranges() {
+            writeln!(f, "{:03X}-{:03X} {access}", range.start(), range.end())?;
+        }
+        Ok(())
+    }
+}
+
+/// A line of a coverage map that isn't `<start>-<end> <flags>`, counting
+/// from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseCoverageError {
+    pub line: usize,
+}
+
+impl fmt::Display for ParseCoverageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid coverage map on line {}", self.line)
+    }
+}
+
+impl<const SIZE: usize> FromStr for Coverage<SIZE> {
+    type Err = ParseCoverageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut coverage = Self::default();
+
+        for (index, line) in s.lines().enumerate() {
+            let error = ParseCoverageError { line: index + 1 };
+
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (range, flags) = line.split_once(char::is_whitespace).ok_or(error)?;
+            let (start, end) = range.split_once('-').ok_or(error)?;
+            let start = u16::from_str_radix(start, 16).map_err(|_| error)?;
+            let end = u16::from_str_radix(end, 16).map_err(|_| error)?;
+            if start > end || end as usize >= SIZE {
+                return Err(error);
+            }
+
+            let mut access = Access::NONE;
+            for flag in flags.trim().chars() {
+                match flag {
+                    'x' => access.executed = true,
+                    'r' => access.read = true,
+                    'w' => access.written = true,
+                    _ => return Err(error),
+                }
+            }
+
+            for address in start..=end {
+                coverage.mark(address, |existing| *existing = access);
+            }
+        }
+
+        Ok(coverage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Emulator;
+
+    #[test]
+    fn marks_code_and_data() {
+        let mut emulator = Emulator::new();
+        let mut coverage: Coverage = Coverage::new();
+        emulator.reset();
+        emulator
+            .load_rom(&[
+                0xA2, 0x0A, // 200: LD I, 20A
+                0xD0, 0x02, // 202: DRW V0, V0, 2
+                0xF0, 0x33, // 204: LD B, V0
+                0x12, 0x06, // 206: JP 206
+                0x00, 0x00, // 208
+                0xFF, 0x81, // 20A: sprite
+            ])
+            .unwrap();
+
+        for _ in 0..5 {
+            emulator.execute_instruction_cycle_with(&mut coverage);
+        }
+
+        assert_eq!(coverage.to_string(), "200-207 x\n20A-20B rw\n20C-20C w\n");
+        assert!(coverage.get(0x208).is_none(), "never reached");
+    }
+
+    #[test]
+    fn round_trip() {
+        let map = "200-245 x\n246-24B r\n300-302 rw\n";
+        let coverage: Coverage = map.parse().unwrap();
+
+        assert_eq!(coverage.to_string(), map);
+        assert!(coverage.get(0x247).read);
+        assert_eq!(
+            "# comment\n\n200-201 xw # trailing\n"
+                .parse::<Coverage>()
+                .unwrap()
+                .get(0x201),
+            Access {
+                executed: true,
+                read: false,
+                written: true,
+            }
+        );
+        assert_eq!(
+            "200-201 x\n201-200 x\n".parse::<Coverage>(),
+            Err(ParseCoverageError { line: 2 })
+        );
+        assert_eq!(
+            "200-201 q".parse::<Coverage>(),
+            Err(ParseCoverageError { line: 1 })
+        );
+    }
+
+    #[test]
+    fn merge() {
+        let mut first: Coverage = "200-203 x\n".parse().unwrap();
+        first.merge(&"202-205 r\n".parse().unwrap());
+
+        assert_eq!(first.to_string(), "200-201 x\n202-203 xr\n204-205 r\n");
+    }
+
+    #[test]
+    fn larger_memory() {
+        let mut coverage = Coverage::<0x2000>::new();
+        coverage.on_memory_write(0x1800, 0);
+
+        assert!(coverage.get(0x1800).written);
+        assert!(coverage.get(0x0800).is_none(), "not aliased");
+        assert_eq!(coverage.to_string(), "1800-1800 w\n");
+        assert_eq!("1800-1800 w\n".parse(), Ok(coverage));
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::OnceLock;
+
+use serde::de::{self, Unexpected};
+use serde::{Deserialize, Deserializer};
+
+use crate::palette::{parse_color, Palette};
+use crate::quirks::Quirks;
+use crate::rom_hash::RomHash;
+
+const PROGRAMS: &str = include_str!("../database/programs.json");
+const HASHES: &str = include_str!("../database/sha1-hashes.json");
+const PLATFORMS: &str = include_str!("../database/platforms.json");
+
+/// The CHIP-8 keys a game uses for directions and buttons, so frontends can
+/// offer arrow keys next to the hex keypad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(default)]
+pub struct KeyMapping {
+    #[serde(deserialize_with = "key")]
+    pub up: Option<u8>,
+    #[serde(deserialize_with = "key")]
+    pub down: Option<u8>,
+    #[serde(deserialize_with = "key")]
+    pub left: Option<u8>,
+    #[serde(deserialize_with = "key")]
+    pub right: Option<u8>,
+    #[serde(deserialize_with = "key")]
+    pub a: Option<u8>,
+    #[serde(deserialize_with = "key")]
+    pub b: Option<u8>,
+}
+
+/// A key of the hex keypad, so frontends can press it without checking.
+fn key<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u8>, D::Error> {
+    match Option::<u8>::deserialize(deserializer)? {
+        Some(key) if key > 0xF => Err(de::Error::invalid_value(
+            Unexpected::Unsigned(key.into()),
+            &"a key from 0 to 15",
+        )),
+        key => Ok(key),
+    }
+}
+
+/// What the database knows about a ROM. Anything it doesn't say is `None`
+/// and left to the frontend's defaults.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomInfo {
+    pub title: String,
+    pub description: Option<String>,
+    pub release: Option<String>,
+    pub authors: Vec<String>,
+    /// Id of the first platform the ROM runs on, like `originalChip8`.
+    pub platform: Option<String>,
+    pub quirks: Option<Quirks>,
+    /// Instruction cycles per frame.
+    pub tickrate: Option<u32>,
+    pub keys: KeyMapping,
+    pub palette: Option<Palette>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Program {
+    title: String,
+    description: Option<String>,
+    release: Option<String>,
+    #[serde(default)]
+    authors: Vec<String>,
+    #[serde(default)]
+    roms: HashMap<String, Rom>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Rom {
+    #[serde(default)]
+    platforms: Vec<String>,
+    tickrate: Option<u32>,
+    #[serde(default)]
+    keys: KeyMapping,
+    colors: Option<Colors>,
+    #[serde(default)]
+    quirky_platforms: HashMap<String, QuirkOverrides>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Colors {
+    #[serde(default)]
+    pixels: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Platform {
+    id: String,
+    default_tickrate: Option<u32>,
+    quirks: QuirkOverrides,
+}
+
+/// Quirks as the database names them. Platforms list all of them, ROMs only
+/// the ones they need changed.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QuirkOverrides {
+    shift: Option<bool>,
+    memory_leave_i_unchanged: Option<bool>,
+    wrap: Option<bool>,
+    jump: Option<bool>,
+    logic: Option<bool>,
+}
+
+impl QuirkOverrides {
+    fn apply(&self, quirks: &mut Quirks) {
+        // `memoryIncrementByX` has no counterpart, I always moves past the
+        // last register unless it's left unchanged.
+        let overrides = [
+            (self.shift, &mut quirks.shift_vy, true),
+            (
+                self.memory_leave_i_unchanged,
+                &mut quirks.load_store_increments_i,
+                true,
+            ),
+            (self.wrap, &mut quirks.clip_sprites, true),
+            (self.jump, &mut quirks.jump_vx, false),
+            (self.logic, &mut quirks.logic_resets_vf, false),
+        ];
+        for (value, quirk, inverted) in overrides {
+            if let Some(value) = value {
+                *quirk = value != inverted;
+            }
+        }
+    }
+}
+
+/// ROM metadata in the format of the community
+/// [chip-8-database](https://github.com/chip-8/chip-8-database), keyed by
+/// the SHA-1 of the ROM.
+#[derive(Debug)]
+pub struct RomDatabase {
+    programs: Vec<Program>,
+    hashes: HashMap<String, usize>,
+    platforms: HashMap<String, Platform>,
+}
+
+impl RomDatabase {
+    /// The database compiled into the crate, from `database/`.
+    pub fn builtin() -> &'static Self {
+        static DATABASE: OnceLock<RomDatabase> = OnceLock::new();
+        DATABASE.get_or_init(|| {
+            Self::from_json(PROGRAMS, HASHES, PLATFORMS).expect("built in database is valid")
+        })
+    }
+
+    /// Reads the database's `programs.json`, `sha1-hashes.json` and
+    /// `platforms.json`.
+    pub fn from_json(programs: &str, hashes: &str, platforms: &str) -> io::Result<Self> {
+        let programs: Vec<Program> = serde_json::from_str(programs)?;
+        let hashes: HashMap<String, usize> = serde_json::from_str(hashes)?;
+        let platforms: Vec<Platform> = serde_json::from_str(platforms)?;
+
+        if let Some(hash) = hashes.iter().find(|(_, &index)| index >= programs.len()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} points past the last program", hash.0),
+            ));
+        }
+
+        Ok(Self {
+            programs,
+            hashes,
+            platforms: platforms
+                .into_iter()
+                .map(|platform| (platform.id.clone(), platform))
+                .collect(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    pub fn lookup(&self, hash: &RomHash) -> Option<RomInfo> {
+        let hash = hash.to_string();
+        let program = &self.programs[*self.hashes.get(&hash)?];
+        let rom = program.roms.get(&hash);
+
+        let platform_id = rom.and_then(|rom| rom.platforms.first());
+        let platform = platform_id.and_then(|id| self.platforms.get(id));
+
+        let quirks = platform.map(|platform| {
+            let mut quirks = Quirks::NONE;
+            platform.quirks.apply(&mut quirks);
+            if let Some(overrides) = rom.and_then(|rom| rom.quirky_platforms.get(&platform.id)) {
+                overrides.apply(&mut quirks);
+            }
+            quirks
+        });
+
+        Some(RomInfo {
+            title: program.title.clone(),
+            description: program.description.clone(),
+            release: program.release.clone(),
+            authors: program.authors.clone(),
+            platform: platform_id.cloned(),
+            quirks,
+            tickrate: rom
+                .and_then(|rom| rom.tickrate)
+                .or(platform.and_then(|platform| platform.default_tickrate)),
+            keys: rom.map(|rom| rom.keys).unwrap_or_default(),
+            palette: rom.and_then(|rom| rom.colors.as_ref()).and_then(|colors| {
+                match colors.pixels.as_slice() {
+                    [off, on, ..] => Some(Palette::new(parse_color(off)?, parse_color(on)?)),
+                    _ => None,
+                }
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAMS: &str = r##"[
+        {
+            "title": "Test",
+            "authors": ["Someone"],
+            "roms": {
+                "a9993e364706816aba3e25717850c26c9cd0d89d": {
+                    "file": "test.ch8",
+                    "platforms": ["superchip", "xochip"],
+                    "keys": { "left": 4, "right": 6, "player2Up": 1 },
+                    "colors": { "pixels": ["#101010", "#e0c040"], "buzzer": "#ffaa00" },
+                    "quirkyPlatforms": { "superchip": { "wrap": true } }
+                }
+            }
+        }
+    ]"##;
+    const HASHES: &str = r#"{ "a9993e364706816aba3e25717850c26c9cd0d89d": 0 }"#;
+    const PLATFORMS: &str = r#"[
+        {
+            "id": "superchip",
+            "name": "SUPER-CHIP 1.1",
+            "defaultTickrate": 30,
+            "quirks": {
+                "shift": true,
+                "memoryIncrementByX": false,
+                "memoryLeaveIUnchanged": true,
+                "wrap": false,
+                "jump": true,
+                "vblank": false,
+                "logic": false
+            }
+        }
+    ]"#;
+
+    #[test]
+    fn lookup() {
+        let database = RomDatabase::from_json(PROGRAMS, HASHES, PLATFORMS).unwrap();
+        let info = database.lookup(&RomHash::of(b"abc")).unwrap();
+
+        assert_eq!(info.title, "Test");
+        assert_eq!(info.platform.as_deref(), Some("superchip"));
+        assert_eq!(
+            info.quirks,
+            Some(Quirks {
+                clip_sprites: false,
+                ..Quirks::SCHIP
+            }),
+            "platform quirks with the rom's overrides"
+        );
+        assert_eq!(info.tickrate, Some(30), "platform default");
+        assert_eq!(info.keys.left, Some(4));
+        assert_eq!(info.keys.up, None);
+        assert_eq!(
+            info.palette,
+            Some(Palette::new([0x10, 0x10, 0x10], [0xe0, 0xc0, 0x40]))
+        );
+
+        assert_eq!(database.lookup(&RomHash::of(b"abd")), None);
+    }
+
+    #[test]
+    fn dangling_hash() {
+        let hashes = r#"{ "a9993e364706816aba3e25717850c26c9cd0d89d": 1 }"#;
+
+        assert!(RomDatabase::from_json(PROGRAMS, hashes, PLATFORMS).is_err());
+    }
+
+    #[test]
+    fn key_out_of_range() {
+        let programs = PROGRAMS.replace(r#""left": 4"#, r#""left": 16"#);
+
+        let err = RomDatabase::from_json(&programs, HASHES, PLATFORMS).unwrap_err();
+        assert!(err.to_string().contains("a key from 0 to 15"), "{err}");
+    }
+
+    #[test]
+    fn builtin() {
+        let database = RomDatabase::builtin();
+        assert!(!database.is_empty());
+
+        for &index in database.hashes.values() {
+            for rom in database.programs[index].roms.values() {
+                for platform in &rom.platforms {
+                    assert!(
+                        database.platforms.contains_key(platform),
+                        "unknown platform {platform}"
+                    );
+                }
+            }
+        }
+    }
+}
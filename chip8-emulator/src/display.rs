@@ -1,12 +1,46 @@
+use core::fmt::{self, Write};
+
 pub const DISPLAY_WIDTH: usize = 64;
 pub const DISPLAY_HEIGHT: usize = 32;
 pub const DISPLAY_PIXELS: usize = DISPLAY_WIDTH * DISPLAY_HEIGHT;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Display {
+    #[cfg_attr(feature = "serde", serde(with = "serde_big_array::BigArray"))]
     buffer: [bool; DISPLAY_PIXELS],
 }
 
+/// One byte per pixel, 0 for off and 1 for on, with every pixel repeated
+/// `scale` times in both directions.
+#[cfg(any(feature = "png", feature = "gif"))]
+pub(crate) fn indexed_pixels(buffer: &[bool; DISPLAY_PIXELS], scale: usize) -> Vec<u8> {
+    let width = DISPLAY_WIDTH * scale;
+    let mut pixels = vec![0; width * DISPLAY_HEIGHT * scale];
+
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let x = i % width / scale;
+        let y = i / width / scale;
+        *pixel = buffer[x + y * DISPLAY_WIDTH] as u8;
+    }
+
+    pixels
+}
+
+/// Draws the framebuffer as text, `#` for lit pixels and `.` for dark ones,
+/// one line per row.
+impl fmt::Display for Display {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.buffer.chunks(DISPLAY_WIDTH) {
+            for &pixel in row {
+                f.write_char(if pixel { '#' } else { '.' })?;
+            }
+            f.write_char('\n')?;
+        }
+        Ok(())
+    }
+}
+
 impl Display {
     pub const fn new() -> Self {
         Self {
@@ -42,16 +76,12 @@ impl Display {
     pub fn draw(&mut self, x: usize, y: usize, sprites: &[u8]) -> bool {
         let mut collision = false;
 
-        let rows = (0..sprites.len())
-            .map(|row| (y + row).rem_euclid(DISPLAY_HEIGHT))
-            .collect::<Vec<usize>>();
-        let cols = (0..8)
-            .map(|col| (x + col).rem_euclid(DISPLAY_WIDTH))
-            .collect::<Vec<usize>>();
+        for (j, sprite) in sprites.iter().enumerate() {
+            let y = (y + j).rem_euclid(DISPLAY_HEIGHT);
 
-        for (j, &y) in rows.iter().enumerate() {
-            for (i, &x) in cols.iter().enumerate() {
-                let value = (sprites[j] >> (7 - i)) & 0x01 > 0;
+            for i in 0..8 {
+                let x = (x + i).rem_euclid(DISPLAY_WIDTH);
+                let value = (sprite >> (7 - i)) & 0x01 > 0;
 
                 collision |= self.xor_pixel(x, y, value);
             }
@@ -59,36 +89,38 @@ impl Display {
 
         collision
     }
-}
 
-pub const FONT_SPRITES: [u8; 5 * 16] = [
-    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-    0x20, 0x60, 0x20, 0x20, 0x70, // 1
-    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-    0xF0, 0x80, 0x80, 0x80, 0xF0, // c
-    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-];
+    /// Like `draw`, but the parts of the sprite past the right and bottom
+    /// edges are cut off instead of wrapping around.
+    pub fn draw_clipped(&mut self, x: usize, y: usize, sprites: &[u8]) -> bool {
+        let x = x.rem_euclid(DISPLAY_WIDTH);
+        let y = y.rem_euclid(DISPLAY_HEIGHT);
+        let rows = sprites.len().min(DISPLAY_HEIGHT - y);
+        let columns = 8.min(DISPLAY_WIDTH - x);
+        let mut collision = false;
+
+        for (j, sprite) in sprites[..rows].iter().enumerate() {
+            for i in 0..columns {
+                let value = (sprite >> (7 - i)) & 0x01 > 0;
+
+                collision |= self.xor_pixel(x + i, y + j, value);
+            }
+        }
+
+        collision
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::font::CHIP48_SMALL;
 
     #[test]
     fn display_digit_0() {
         let mut display = Display::new();
 
-        let collision = display.draw(0, 0, &FONT_SPRITES[0..5]);
+        let collision = display.draw(0, 0, &CHIP48_SMALL[0..5]);
 
         assert!(!collision);
 
@@ -116,7 +148,7 @@ mod tests {
     fn display_digit_0_wrapped() {
         let mut display = Display::new();
 
-        let collision = display.draw(DISPLAY_WIDTH - 2, DISPLAY_HEIGHT - 2, &FONT_SPRITES[0..5]);
+        let collision = display.draw(DISPLAY_WIDTH - 2, DISPLAY_HEIGHT - 2, &CHIP48_SMALL[0..5]);
 
         assert!(!collision);
 
@@ -139,4 +171,40 @@ mod tests {
         assert!(display.buffer[DISPLAY_WIDTH * 2]);
         assert!(display.buffer[1 + DISPLAY_WIDTH * 2]);
     }
+
+    #[test]
+    fn display_digit_0_clipped() {
+        let mut display = Display::new();
+
+        let collision =
+            display.draw_clipped(DISPLAY_WIDTH - 2, DISPLAY_HEIGHT - 2, &CHIP48_SMALL[0..5]);
+
+        assert!(!collision);
+
+        assert!(display.buffer[DISPLAY_WIDTH - 2 + DISPLAY_WIDTH * (DISPLAY_HEIGHT - 2)]);
+        assert!(display.buffer[DISPLAY_WIDTH - 1 + DISPLAY_WIDTH * (DISPLAY_HEIGHT - 2)]);
+        assert!(display.buffer[DISPLAY_WIDTH - 2 + DISPLAY_WIDTH * (DISPLAY_HEIGHT - 1)]);
+        assert_eq!(
+            display.buffer.iter().filter(|&&pixel| pixel).count(),
+            3,
+            "nothing wrapped around"
+        );
+
+        display.cls();
+        display.draw_clipped(DISPLAY_WIDTH + 1, 0, &[0x80]);
+        assert!(display.buffer[1], "the position itself wraps");
+    }
+
+    #[test]
+    fn ascii() {
+        let mut display = Display::new();
+        display.draw(1, 0, &[0xC0]);
+
+        let text = display.to_string();
+        let mut lines = text.lines();
+
+        assert_eq!(text.lines().count(), DISPLAY_HEIGHT);
+        assert_eq!(&lines.next().unwrap()[..4], ".##.");
+        assert_eq!(lines.next().unwrap(), ".".repeat(DISPLAY_WIDTH));
+    }
 }
@@ -0,0 +1,111 @@
+use embedded_graphics::{
+    pixelcolor::BinaryColor,
+    prelude::{DrawTarget, Point, PointsIter, Size},
+    primitives::Rectangle,
+    Drawable,
+};
+
+use crate::display::{Display, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+
+/// Draws the framebuffer onto any monochrome `DrawTarget`, e.g. an SSD1306
+/// driver, with every CHIP-8 pixel blown up to a `scale` x `scale` square
+/// whose top left corner is at `top_left`.
+#[derive(Debug, Clone, Copy)]
+pub struct ScaledDisplay<'a> {
+    display: &'a Display,
+    top_left: Point,
+    scale: u32,
+}
+
+impl<'a> ScaledDisplay<'a> {
+    pub fn new(display: &'a Display, top_left: Point, scale: u32) -> Self {
+        Self {
+            display,
+            top_left,
+            scale: scale.max(1),
+        }
+    }
+
+    pub fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(
+            self.top_left,
+            Size::new(
+                DISPLAY_WIDTH as u32 * self.scale,
+                DISPLAY_HEIGHT as u32 * self.scale,
+            ),
+        )
+    }
+}
+
+impl Drawable for ScaledDisplay<'_> {
+    type Color = BinaryColor;
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        let area = self.bounding_box();
+        let scale = self.scale as usize;
+        let buffer = self.display.get_buffer();
+
+        let colors = area.points().map(|point| {
+            let x = (point.x - self.top_left.x) as usize / scale;
+            let y = (point.y - self.top_left.y) as usize / scale;
+
+            BinaryColor::from(buffer[x + y * DISPLAY_WIDTH])
+        });
+
+        target.fill_contiguous(&area, colors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::mock_display::MockDisplay;
+
+    use super::*;
+    use crate::font::CHIP48_SMALL;
+
+    #[test]
+    fn draws_every_pixel() {
+        let mut display = Display::new();
+        display.draw(0, 0, &CHIP48_SMALL[0..5]);
+
+        let mut target = MockDisplay::<BinaryColor>::new();
+        ScaledDisplay::new(&display, Point::zero(), 1)
+            .draw(&mut target)
+            .unwrap();
+
+        assert_eq!(
+            target.affected_area(),
+            Rectangle::new(Point::zero(), Size::new(64, 32))
+        );
+        assert_eq!(target.get_pixel(Point::new(0, 0)), Some(BinaryColor::On));
+        assert_eq!(target.get_pixel(Point::new(1, 1)), Some(BinaryColor::Off));
+        assert_eq!(target.get_pixel(Point::new(3, 4)), Some(BinaryColor::On));
+        assert_eq!(target.get_pixel(Point::new(63, 31)), Some(BinaryColor::Off));
+    }
+
+    #[test]
+    fn scale_and_offset() {
+        let mut display = Display::new();
+        display.draw(0, 0, &[0x80]);
+
+        let mut target = MockDisplay::<BinaryColor>::new();
+        target.set_allow_out_of_bounds_drawing(true);
+        ScaledDisplay::new(&display, Point::new(2, 3), 2)
+            .draw(&mut target)
+            .unwrap();
+
+        assert_eq!(
+            target.get_pixel(Point::new(1, 3)),
+            None,
+            "left of the offset"
+        );
+        assert_eq!(target.get_pixel(Point::new(2, 3)), Some(BinaryColor::On));
+        assert_eq!(target.get_pixel(Point::new(3, 4)), Some(BinaryColor::On));
+        assert_eq!(target.get_pixel(Point::new(4, 3)), Some(BinaryColor::Off));
+        assert_eq!(target.get_pixel(Point::new(2, 5)), Some(BinaryColor::Off));
+    }
+}
@@ -0,0 +1,266 @@
+use core::fmt;
+
+use crate::PROGRAM_START;
+
+pub const SMALL_FONT_SIZE: usize = 5 * 16;
+pub const LARGE_FONT_SIZE: usize = 10 * 16;
+
+/// Fonts live in the interpreter area, below the program.
+const FONT_AREA_END: usize = PROGRAM_START as usize;
+
+/// A font whose small and large tables share bytes, so loading one would
+/// overwrite glyphs of the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FontOverlapError;
+
+impl fmt::Display for FontOverlapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("font tables overlap")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FontOverlapError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Font {
+    #[cfg_attr(feature = "serde", serde(with = "serde_big_array::BigArray"))]
+    small: [u8; SMALL_FONT_SIZE],
+    #[cfg_attr(feature = "serde", serde(with = "serde_big_array::BigArray"))]
+    large: [u8; LARGE_FONT_SIZE],
+    small_address: u16,
+    large_address: u16,
+}
+
+impl Font {
+    pub const fn new(small: [u8; SMALL_FONT_SIZE], large: [u8; LARGE_FONT_SIZE]) -> Self {
+        Self {
+            small,
+            large,
+            small_address: 0x000,
+            large_address: SMALL_FONT_SIZE as u16,
+        }
+    }
+
+    /// Builds a font from user supplied glyph data, e.g. dumped from another
+    /// interpreter. Returns `None` when a table isn't exactly the expected
+    /// size; a missing large font falls back to the SCHIP digits.
+    pub fn from_bytes(small: &[u8], large: Option<&[u8]>) -> Option<Self> {
+        let small = small.try_into().ok()?;
+        let large = match large {
+            Some(large) => large.try_into().ok()?,
+            None => SCHIP_LARGE,
+        };
+
+        Some(Self::new(small, large))
+    }
+
+    /// Returns `None` when the table wouldn't fit below the program at 0x200.
+    pub const fn with_small_address(mut self, address: u16) -> Option<Self> {
+        if address as usize + SMALL_FONT_SIZE > FONT_AREA_END {
+            return None;
+        }
+        self.small_address = address;
+        Some(self)
+    }
+
+    /// Returns `None` when the table wouldn't fit below the program at 0x200.
+    pub const fn with_large_address(mut self, address: u16) -> Option<Self> {
+        if address as usize + LARGE_FONT_SIZE > FONT_AREA_END {
+            return None;
+        }
+        self.large_address = address;
+        Some(self)
+    }
+
+    /// Whether the small and large tables share any bytes, so loading one
+    /// would overwrite glyphs of the other.
+    pub fn tables_overlap(&self) -> bool {
+        let small = self.small_address as usize..self.small_address as usize + SMALL_FONT_SIZE;
+        let large = self.large_address as usize..self.large_address as usize + LARGE_FONT_SIZE;
+        small.start < large.end && large.start < small.end
+    }
+
+    pub fn small(&self) -> &[u8; SMALL_FONT_SIZE] {
+        &self.small
+    }
+
+    pub fn large(&self) -> &[u8; LARGE_FONT_SIZE] {
+        &self.large
+    }
+
+    pub fn small_address(&self) -> u16 {
+        self.small_address
+    }
+
+    pub fn large_address(&self) -> u16 {
+        self.large_address
+    }
+
+    /// Address of the 4x5 glyph for the low nibble of `digit`.
+    pub fn small_glyph_address(&self, digit: u8) -> u16 {
+        self.small_address + (digit & 0xF) as u16 * 5
+    }
+
+    /// Address of the 8x10 glyph for the low nibble of `digit`.
+    pub fn large_glyph_address(&self, digit: u8) -> u16 {
+        self.large_address + (digit & 0xF) as u16 * 10
+    }
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Self::new(CHIP48_SMALL, SCHIP_LARGE)
+    }
+}
+
+pub const VIP_SMALL: [u8; SMALL_FONT_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x60, 0x20, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0xA0, 0xA0, 0xF0, 0x20, 0x20, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x10, 0x10, 0x10, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xF0, 0x50, 0x70, 0x50, 0xF0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xF0, 0x50, 0x50, 0x50, 0xF0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+pub const DREAM_6800_SMALL: [u8; SMALL_FONT_SIZE] = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+    0x40, 0x40, 0x40, 0x40, 0x40, // 1
+    0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+    0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+    0x80, 0xA0, 0xA0, 0xE0, 0x20, // 4
+    0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+    0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+    0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+    0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+    0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+    0xC0, 0xA0, 0xE0, 0xA0, 0xC0, // B
+    0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+    0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // D
+    0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+    0xE0, 0x80, 0xC0, 0x80, 0x80, // F
+];
+
+pub const ETI_660_SMALL: [u8; SMALL_FONT_SIZE] = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+    0x20, 0x20, 0x20, 0x20, 0x20, // 1
+    0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+    0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+    0xA0, 0xA0, 0xE0, 0x20, 0x20, // 4
+    0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+    0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+    0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+    0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+    0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+    0x80, 0x80, 0xE0, 0xA0, 0xE0, // B
+    0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+    0x20, 0x20, 0xE0, 0xA0, 0xE0, // D
+    0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+    0xE0, 0x80, 0xC0, 0x80, 0x80, // F
+];
+
+pub const CHIP48_SMALL: [u8; SMALL_FONT_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // c
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+// SCHIP 1.1 only shipped big digits, A-F follow the glyphs Octo uses.
+pub const SCHIP_LARGE: [u8; LARGE_FONT_SIZE] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glyph_addresses() {
+        let font = Font::default()
+            .with_small_address(0x050)
+            .and_then(|font| font.with_large_address(0x0A0))
+            .unwrap();
+
+        assert_eq!(font.small_glyph_address(0x0), 0x050);
+        assert_eq!(font.small_glyph_address(0xF), 0x050 + 75);
+        assert_eq!(font.large_glyph_address(0x3), 0x0A0 + 30);
+        assert_eq!(
+            font.small_glyph_address(0x13),
+            0x050 + 15,
+            "only the low nibble selects a glyph"
+        );
+    }
+
+    #[test]
+    fn placement() {
+        assert!(!Font::default().tables_overlap());
+        assert!(Font::default()
+            .with_small_address(0x0A0)
+            .unwrap()
+            .tables_overlap());
+        assert!(!Font::default()
+            .with_small_address(0x1B0)
+            .and_then(|font| font.with_large_address(0x000))
+            .unwrap()
+            .tables_overlap());
+    }
+
+    #[test]
+    fn placement_past_the_program_start() {
+        assert_eq!(Font::default().with_large_address(0x180), None);
+        assert_eq!(Font::default().with_small_address(0x1B1), None);
+        assert!(Font::default().with_large_address(0x160).is_some());
+    }
+
+    #[test]
+    fn from_bytes() {
+        let font = Font::from_bytes(&VIP_SMALL, None).expect("valid font");
+
+        assert_eq!(font.small(), &VIP_SMALL);
+        assert_eq!(font.large(), &SCHIP_LARGE);
+
+        assert!(Font::from_bytes(&VIP_SMALL[1..], None).is_none());
+        assert!(Font::from_bytes(&VIP_SMALL, Some(&SCHIP_LARGE[..100])).is_none());
+    }
+}
@@ -1,4 +1,9 @@
-#[derive(Debug)]
+use core::fmt;
+
+use crate::bus::Bus;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Instruction(pub u8, pub u8, pub u8, pub u8);
 
 impl Instruction {
@@ -12,9 +17,9 @@ impl Instruction {
         )
     }
 
-    pub fn read(memory: &[u8; 4096], location: u16) -> Self {
-        let first_byte = memory[location as usize];
-        let second_byte = memory[location as usize + 1];
+    pub fn read<B: Bus + ?Sized>(memory: &B, location: u16) -> Self {
+        let first_byte = memory.read(location);
+        let second_byte = memory.read(location.wrapping_add(1));
 
         Self(
             (first_byte & 0xF0) >> 4,
@@ -24,6 +29,58 @@ impl Instruction {
         )
     }
 
+    pub fn opcode(&self) -> u16 {
+        (self.0 as u16) << 12 | self.nnn()
+    }
+
+    /// The opcode's entry in the usual reference tables, like `8xy4`, or
+    /// `None` for opcodes the interpreter doesn't know.
+    pub fn pattern(&self) -> Option<&'static str> {
+        let pattern = match *self {
+            Self(0x0, 0x0, 0xE, 0x0) => "00E0",
+            Self(0x0, 0x0, 0xE, 0xE) => "00EE",
+            Self(0x0, _, _, _) => "0nnn",
+            Self(0x1, _, _, _) => "1nnn",
+            Self(0x2, _, _, _) => "2nnn",
+            Self(0x3, _, _, _) => "3xkk",
+            Self(0x4, _, _, _) => "4xkk",
+            Self(0x5, _, _, 0x0) => "5xy0",
+            Self(0x6, _, _, _) => "6xkk",
+            Self(0x7, _, _, _) => "7xkk",
+            Self(0x8, _, _, 0x0) => "8xy0",
+            Self(0x8, _, _, 0x1) => "8xy1",
+            Self(0x8, _, _, 0x2) => "8xy2",
+            Self(0x8, _, _, 0x3) => "8xy3",
+            Self(0x8, _, _, 0x4) => "8xy4",
+            Self(0x8, _, _, 0x5) => "8xy5",
+            Self(0x8, _, _, 0x6) => "8xy6",
+            Self(0x8, _, _, 0x7) => "8xy7",
+            Self(0x8, _, _, 0xE) => "8xyE",
+            Self(0x9, _, _, 0x0) => "9xy0",
+            Self(0xA, _, _, _) => "Annn",
+            Self(0xB, _, _, _) => "Bnnn",
+            Self(0xC, _, _, _) => "Cxkk",
+            Self(0xD, _, _, _) => "Dxyn",
+            Self(0xE, _, 0x9, 0xE) => "Ex9E",
+            Self(0xE, _, 0xA, 0x1) => "ExA1",
+            Self(0xF, _, 0x0, 0x7) => "Fx07",
+            Self(0xF, _, 0x0, 0xA) => "Fx0A",
+            Self(0xF, _, 0x1, 0x5) => "Fx15",
+            Self(0xF, _, 0x1, 0x8) => "Fx18",
+            Self(0xF, _, 0x1, 0xE) => "Fx1E",
+            Self(0xF, _, 0x2, 0x9) => "Fx29",
+            Self(0xF, _, 0x3, 0x0) => "Fx30",
+            Self(0xF, _, 0x3, 0x3) => "Fx33",
+            Self(0xF, _, 0x5, 0x5) => "Fx55",
+            Self(0xF, _, 0x6, 0x5) => "Fx65",
+            Self(0xF, _, 0x7, 0x5) => "Fx75",
+            Self(0xF, _, 0x8, 0x5) => "Fx85",
+            _ => return None,
+        };
+
+        Some(pattern)
+    }
+
     pub fn nnn(&self) -> u16 {
         (self.1 as u16) << 8 | (self.2 as u16) << 4 | (self.3 as u16)
     }
@@ -43,4 +100,112 @@ impl Instruction {
     pub fn kk(&self) -> u8 {
         self.2 << 4 | self.3
     }
+
+    /// Writes the mnemonic like `Display`, naming addresses for which
+    /// `label` returns `Some`, e.g. `CALL sub_2F6` instead of `CALL 2F6`.
+    pub fn write_mnemonic<L: fmt::Display>(
+        &self,
+        f: &mut dyn fmt::Write,
+        label: impl Fn(u16) -> Option<L>,
+    ) -> fmt::Result {
+        let (x, y, kk, n) = (self.x(), self.y(), self.kk(), self.n());
+        let nnn = Target(self.nnn(), label(self.nnn()));
+
+        match *self {
+            Self(0x0, 0x0, 0xE, 0x0) => write!(f, "CLS"),
+            Self(0x0, 0x0, 0xE, 0xE) => write!(f, "RET"),
+            Self(0x0, _, _, _) => write!(f, "SYS {nnn}"),
+            Self(0x1, _, _, _) => write!(f, "JP {nnn}"),
+            Self(0x2, _, _, _) => write!(f, "CALL {nnn}"),
+            Self(0x3, _, _, _) => write!(f, "SE V{x:X}, #{kk:02X}"),
+            Self(0x4, _, _, _) => write!(f, "SNE V{x:X}, #{kk:02X}"),
+            Self(0x5, _, _, 0x0) => write!(f, "SE V{x:X}, V{y:X}"),
+            Self(0x6, _, _, _) => write!(f, "LD V{x:X}, #{kk:02X}"),
+            Self(0x7, _, _, _) => write!(f, "ADD V{x:X}, #{kk:02X}"),
+            Self(0x8, _, _, 0x0) => write!(f, "LD V{x:X}, V{y:X}"),
+            Self(0x8, _, _, 0x1) => write!(f, "OR V{x:X}, V{y:X}"),
+            Self(0x8, _, _, 0x2) => write!(f, "AND V{x:X}, V{y:X}"),
+            Self(0x8, _, _, 0x3) => write!(f, "XOR V{x:X}, V{y:X}"),
+            Self(0x8, _, _, 0x4) => write!(f, "ADD V{x:X}, V{y:X}"),
+            Self(0x8, _, _, 0x5) => write!(f, "SUB V{x:X}, V{y:X}"),
+            Self(0x8, _, _, 0x6) => write!(f, "SHR V{x:X}, V{y:X}"),
+            Self(0x8, _, _, 0x7) => write!(f, "SUBN V{x:X}, V{y:X}"),
+            Self(0x8, _, _, 0xE) => write!(f, "SHL V{x:X}, V{y:X}"),
+            Self(0x9, _, _, 0x0) => write!(f, "SNE V{x:X}, V{y:X}"),
+            Self(0xA, _, _, _) => write!(f, "LD I, {nnn}"),
+            Self(0xB, _, _, _) => write!(f, "JP V0, {nnn}"),
+            Self(0xC, _, _, _) => write!(f, "RND V{x:X}, #{kk:02X}"),
+            Self(0xD, _, _, _) => write!(f, "DRW V{x:X}, V{y:X}, {n}"),
+            Self(0xE, _, 0x9, 0xE) => write!(f, "SKP V{x:X}"),
+            Self(0xE, _, 0xA, 0x1) => write!(f, "SKNP V{x:X}"),
+            Self(0xF, _, 0x0, 0x7) => write!(f, "LD V{x:X}, DT"),
+            Self(0xF, _, 0x0, 0xA) => write!(f, "LD V{x:X}, K"),
+            Self(0xF, _, 0x1, 0x5) => write!(f, "LD DT, V{x:X}"),
+            Self(0xF, _, 0x1, 0x8) => write!(f, "LD ST, V{x:X}"),
+            Self(0xF, _, 0x1, 0xE) => write!(f, "ADD I, V{x:X}"),
+            Self(0xF, _, 0x2, 0x9) => write!(f, "LD F, V{x:X}"),
+            Self(0xF, _, 0x3, 0x0) => write!(f, "LD HF, V{x:X}"),
+            Self(0xF, _, 0x3, 0x3) => write!(f, "LD B, V{x:X}"),
+            Self(0xF, _, 0x5, 0x5) => write!(f, "LD [I], V{x:X}"),
+            Self(0xF, _, 0x6, 0x5) => write!(f, "LD V{x:X}, [I]"),
+            Self(0xF, _, 0x7, 0x5) => write!(f, "LD R, V{x:X}"),
+            Self(0xF, _, 0x8, 0x5) => write!(f, "LD V{x:X}, R"),
+            _ => write!(f, "DW #{:04X}", self.opcode()),
+        }
+    }
+}
+
+/// Cowgod's mnemonics, e.g. `LD V0, #05` or `DRW V0, V1, 5`. Opcodes the
+/// interpreter doesn't know come out as a `DW` data word.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_mnemonic(f, |_| None::<&str>)
+    }
+}
+
+struct Target<L>(u16, Option<L>);
+
+impl<L: fmt::Display> fmt::Display for Target<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.1 {
+            Some(label) => label.fmt(f),
+            None => write!(f, "{:03X}", self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pattern() {
+        assert_eq!(Instruction::from_opcode(0x00E0).pattern(), Some("00E0"));
+        assert_eq!(Instruction::from_opcode(0x0123).pattern(), Some("0nnn"));
+        assert_eq!(Instruction::from_opcode(0x8AB4).pattern(), Some("8xy4"));
+        assert_eq!(Instruction::from_opcode(0xF265).pattern(), Some("Fx65"));
+        assert_eq!(Instruction::from_opcode(0x5AB1).pattern(), None);
+        assert_eq!(Instruction::from_opcode(0x8AB4).opcode(), 0x8AB4);
+    }
+
+    #[test]
+    fn mnemonic() {
+        assert_eq!(Instruction::from_opcode(0x00EE).to_string(), "RET");
+        assert_eq!(Instruction::from_opcode(0x6A05).to_string(), "LD VA, #05");
+        assert_eq!(
+            Instruction::from_opcode(0xD015).to_string(),
+            "DRW V0, V1, 5"
+        );
+        assert_eq!(Instruction::from_opcode(0x22F6).to_string(), "CALL 2F6");
+        assert_eq!(Instruction::from_opcode(0xF265).to_string(), "LD V2, [I]");
+        assert_eq!(Instruction::from_opcode(0x5AB1).to_string(), "DW #5AB1");
+
+        let mut labeled = String::new();
+        Instruction::from_opcode(0x22F6)
+            .write_mnemonic(&mut labeled, |address| {
+                (address == 0x2F6).then_some("sub_2F6")
+            })
+            .unwrap();
+        assert_eq!(labeled, "CALL sub_2F6");
+    }
 }
@@ -1,4 +1,14 @@
+/// Keys as laid out on the COSMAC VIP hex keypad, indexed by row and then
+/// column of the 4x4 matrix.
+pub const KEYPAD_LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Keypad {
     keys: [bool; 16],
 }
@@ -13,10 +23,10 @@ impl Keypad {
     }
 
     pub fn get_key(&self, key: u8) -> bool {
-        self.keys
+        *self
+            .keys
             .get(key as usize)
             .expect("key should be between 0 and 15")
-            .to_owned()
     }
 
     pub fn key_down(&mut self, key: u8) {
@@ -36,4 +46,56 @@ impl Keypad {
 
         *key = false;
     }
+
+    /// All keys as a bit set, bit `n` being key `n`.
+    pub fn state(&self) -> u16 {
+        self.keys
+            .iter()
+            .enumerate()
+            .fold(0, |state, (key, &pressed)| state | (pressed as u16) << key)
+    }
+
+    pub fn set_state(&mut self, state: u16) {
+        for (key, pressed) in self.keys.iter_mut().enumerate() {
+            *pressed = state & (1 << key) != 0;
+        }
+    }
+
+    /// Updates every key from a 4x4 matrix scan, `is_pressed(row, col)`
+    /// following `KEYPAD_LAYOUT`.
+    pub fn scan_matrix(&mut self, mut is_pressed: impl FnMut(usize, usize) -> bool) {
+        for (row, keys) in KEYPAD_LAYOUT.iter().enumerate() {
+            for (col, &key) in keys.iter().enumerate() {
+                self.keys[key as usize] = is_pressed(row, col);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_round_trip() {
+        let mut keypad = Keypad::new();
+        keypad.key_down(0x0);
+        keypad.key_down(0xA);
+
+        assert_eq!(keypad.state(), 0b0000_0100_0000_0001);
+
+        keypad.set_state(0b1000_0000_0000_0010);
+        assert!(keypad.get_key(0x1));
+        assert!(keypad.get_key(0xF));
+        assert!(!keypad.get_key(0xA));
+    }
+
+    #[test]
+    fn scan_matrix() {
+        let mut keypad = Keypad::new();
+
+        keypad.scan_matrix(|row, col| (row, col) == (3, 1) || (row, col) == (0, 3));
+
+        assert_eq!(keypad.state(), 1 << 0x0 | 1 << 0xC);
+    }
 }
@@ -1,45 +1,167 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![allow(clippy::new_without_default)]
 
+#[cfg(feature = "std")]
+mod analysis;
+#[cfg(feature = "gif")]
+mod animation;
+mod audio;
+mod bus;
+#[cfg(feature = "cartridge")]
+mod cartridge;
+#[cfg(feature = "std")]
+mod cheat;
+mod coverage;
+#[cfg(feature = "database")]
+mod database;
 mod display;
+#[cfg(feature = "embedded-graphics")]
+mod draw_target;
+pub mod font;
 mod instruction;
 mod keypad;
+#[cfg(feature = "std")]
+mod movie;
+mod observer;
+#[cfg(feature = "std")]
+mod octo;
+mod palette;
+#[cfg(feature = "std")]
+mod patch;
+#[cfg(feature = "std")]
+mod platform;
+#[cfg(feature = "std")]
+mod profiler;
+#[cfg(feature = "std")]
+mod quirk_detector;
+mod quirks;
+mod rng;
+mod rom_hash;
+#[cfg(feature = "png")]
+mod screenshot;
+#[cfg(feature = "std")]
+mod wav;
+
+use core::fmt;
+
+use font::{Font, FontOverlapError, CHIP48_SMALL, SCHIP_LARGE};
+use rng::Rng;
+
+#[cfg(feature = "std")]
+pub use analysis::{Analysis, BasicBlock, Exit};
+#[cfg(feature = "gif")]
+pub use animation::GifRecorder;
+pub use audio::{AudioSynth, FrameSound};
+pub use bus::{Bus, Ram};
+#[cfg(feature = "cartridge")]
+pub use cartridge::Cartridge;
+#[cfg(feature = "std")]
+pub use cheat::{Cheat, CheatSearch, CheatTarget, Cheats, Comparison, ParseCheatError};
+pub use coverage::{Access, Coverage, ParseCoverageError};
+#[cfg(feature = "database")]
+pub use database::{KeyMapping, RomDatabase, RomInfo};
+pub use display::{Display, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+#[cfg(feature = "embedded-graphics")]
+pub use draw_target::ScaledDisplay;
+pub use instruction::Instruction;
+pub use keypad::{Keypad, KEYPAD_LAYOUT};
+#[cfg(feature = "std")]
+pub use movie::{Movie, MoviePlayer, MovieRecorder, MAX_MOVIE_FRAMES};
+pub use observer::Observer;
+#[cfg(feature = "std")]
+pub use octo::{assemble_octo, AssembleError};
+pub use palette::Palette;
+#[cfg(feature = "std")]
+pub use patch::{Patch, PatchFormat};
+#[cfg(feature = "std")]
+pub use platform::{guess_platform, Confidence, Platform, PlatformGuess, MAX_CHIP8_ROM_SIZE};
+#[cfg(feature = "std")]
+pub use profiler::{AddressStats, Profiler, SubroutineStats};
+#[cfg(feature = "std")]
+pub use quirk_detector::{ProfileRun, QuirkDetector, QuirkReport, WRAPPING_DRAW};
+pub use quirks::{ParseQuirksError, Quirks};
+pub use rom_hash::{ParseRomHashError, RomHash};
+#[cfg(feature = "std")]
+pub use wav::{WavRecorder, WavWriter};
 
-use display::{Display, FONT_SPRITES};
-use instruction::Instruction;
-use keypad::Keypad;
-use rand::Rng;
-
-const MEMORY_SIZE: usize = 0x1000; // 4kb
 const STACK_SIZE: usize = 0x10; // 16
+const PROGRAM_START: u16 = 0x200;
+
+/// A ROM that doesn't fit between 0x200 and the end of the bus's memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RomTooLargeError {
+    pub size: usize,
+    pub max_size: usize,
+}
+
+impl fmt::Display for RomTooLargeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "rom is {} bytes, only {} fit in memory",
+            self.size, self.max_size
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RomTooLargeError {}
 
 #[derive(Debug)]
-pub struct Emulator {
-    memory: [u8; MEMORY_SIZE],
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Emulator<B: Bus = Ram> {
+    memory: B,
     v_registers: [u8; 16],
     i_register: u16,
     delay_timer: u8,
     sound_timer: u8,
     program_counter: u16,
     stack_pointer: u8,
+    /// How many entries below `stack_pointer` are live calls, at most the
+    /// whole ring once older ones have been overwritten.
+    stack_depth: u8,
     stack: [u16; STACK_SIZE],
+    font: Font,
+    quirks: Quirks,
+    waiting_for_key: bool,
+    rpl_flags: [u8; 16],
+    rng: Rng,
+    frame_cycle: u32,
+    frame_sound: FrameSound,
+    last_frame_sound: FrameSound,
 
     pub display: Display,
     pub keypad: Keypad,
 }
 
 impl Emulator {
-    pub const MEMORY_SIZE: usize = 0x1000; // 4kb
+    pub const MEMORY_SIZE: usize = <Ram>::SIZE; // 4kb
 
     pub const fn new() -> Emulator {
-        Emulator {
-            memory: [0; MEMORY_SIZE],
+        Emulator::with_bus(Ram::new())
+    }
+}
+
+impl<B: Bus> Emulator<B> {
+    pub const fn with_bus(memory: B) -> Self {
+        Self {
+            memory,
             v_registers: [0; 16],
             i_register: 0,
             delay_timer: 0,
             sound_timer: 0,
             program_counter: 0,
             stack_pointer: 0,
+            stack_depth: 0,
             stack: [0; STACK_SIZE],
+            font: Font::new(CHIP48_SMALL, SCHIP_LARGE),
+            quirks: Quirks::NONE,
+            waiting_for_key: false,
+            rpl_flags: [0; 16],
+            rng: Rng::new(Rng::DEFAULT_SEED),
+            frame_cycle: 0,
+            frame_sound: FrameSound::new(false),
+            last_frame_sound: FrameSound::new(false),
 
             display: Display::new(),
             keypad: Keypad::new(),
@@ -47,30 +169,126 @@ impl Emulator {
     }
 
     pub fn reset(&mut self) {
-        self.memory = [0; MEMORY_SIZE];
+        self.memory.clear();
         self.v_registers = [0; 16];
         self.i_register = 0;
         self.delay_timer = 0;
         self.sound_timer = 0;
         self.stack_pointer = 0;
+        self.stack_depth = 0;
         self.stack = [0; STACK_SIZE];
+        self.waiting_for_key = false;
+        self.frame_cycle = 0;
+        self.frame_sound = FrameSound::new(false);
+        self.last_frame_sound = FrameSound::new(false);
 
-        self.memory[0..80].copy_from_slice(&FONT_SPRITES);
-        self.program_counter = 0x200;
+        self.load_font();
+        self.program_counter = PROGRAM_START;
 
         self.display.cls();
         self.keypad.clear();
     }
 
-    pub fn load_rom(&mut self, rom: &[u8]) {
-        self.memory[0x200..0x200 + rom.len()].copy_from_slice(rom);
+    /// Selects the glyphs used by `Fx29`/`Fx30`. The font is copied into memory
+    /// right away and again on every `reset`. Nothing changes when the small
+    /// and large tables overlap.
+    pub fn set_font(&mut self, font: Font) -> Result<(), FontOverlapError> {
+        if font.tables_overlap() {
+            return Err(FontOverlapError);
+        }
+
+        self.font = font;
+        self.load_font();
+        Ok(())
+    }
+
+    pub fn font(&self) -> &Font {
+        &self.font
+    }
+
+    fn load_font(&mut self) {
+        self.memory
+            .load(self.font.small_address(), self.font.small());
+        self.memory
+            .load(self.font.large_address(), self.font.large());
+    }
+
+    /// Selects how the opcodes interpreters disagree on behave. Kept across
+    /// `reset`.
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    pub fn quirks(&self) -> &Quirks {
+        &self.quirks
+    }
+
+    /// Restores RPL flags saved by an earlier run, see
+    /// `Observer::on_save_flags`. Kept across `reset`.
+    pub fn set_rpl_flags(&mut self, flags: [u8; 16]) {
+        self.rpl_flags = flags;
+    }
+
+    /// Where `Fx75` stores registers and `Fx85` loads them from. SCHIP had
+    /// 8 of them, XO-CHIP 16.
+    pub fn rpl_flags(&self) -> &[u8; 16] {
+        &self.rpl_flags
+    }
+
+    /// Reseeds the generator behind `Cxkk`. Emulators seeded with the same
+    /// value and fed the same input behave identically, so frontends should
+    /// pass in some entropy unless they need a reproducible run.
+    pub fn set_rng_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    /// Copies `rom` to 0x200. Nothing is loaded when it's too large to fit,
+    /// rather than letting it wrap around over the font.
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), RomTooLargeError> {
+        let max_size = self.memory.size().saturating_sub(PROGRAM_START as usize);
+        if rom.len() > max_size {
+            return Err(RomTooLargeError {
+                size: rom.len(),
+                max_size,
+            });
+        }
+
+        self.memory.load(PROGRAM_START, rom);
+        Ok(())
+    }
+
+    pub fn memory(&self) -> &B {
+        &self.memory
+    }
+
+    pub fn memory_mut(&mut self) -> &mut B {
+        &mut self.memory
     }
 
     pub fn decrement_timers(&mut self) {
+        self.decrement_timers_with(&mut ());
+    }
+
+    pub fn decrement_timers_with<O: Observer + ?Sized>(&mut self, observer: &mut O) {
         self.delay_timer = self.delay_timer.saturating_sub(1);
+
+        if self.sound_timer == 1 {
+            observer.on_sound_stop();
+        }
         self.sound_timer = self.sound_timer.saturating_sub(1);
+
+        let next_frame_sound = FrameSound::new(self.is_sound_playing());
+        self.last_frame_sound = core::mem::replace(&mut self.frame_sound, next_frame_sound);
+        self.frame_cycle = 0;
     }
 
+    /// The buzzer activity of the frame that ended with the last call to
+    /// `decrement_timers`, ready to be rendered with an `AudioSynth`.
+    pub fn last_frame_sound(&self) -> &FrameSound {
+        &self.last_frame_sound
+    }
+
+    #[cfg(feature = "std")]
     pub fn debug_info(&self) -> String {
         format!(
             "v: {:?}, i: {:?}, sp: {:?}, stack: {:?}, dt: {:?}, pc: {:?}, instruction: {:?}",
@@ -84,27 +302,127 @@ impl Emulator {
         )
     }
 
+    pub fn v_registers(&self) -> &[u8; 16] {
+        &self.v_registers
+    }
+
+    pub fn v_registers_mut(&mut self) -> &mut [u8; 16] {
+        &mut self.v_registers
+    }
+
+    pub fn i_register(&self) -> u16 {
+        self.i_register
+    }
+
+    pub fn program_counter(&self) -> u16 {
+        self.program_counter
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    /// The return addresses currently on the stack, oldest first. Once
+    /// calls have nested deeper than the stack holds, only the latest 16 are
+    /// left.
+    pub fn stack(&self) -> impl Iterator<Item = u16> + '_ {
+        let oldest = self.stack_pointer as usize + STACK_SIZE - self.stack_depth as usize;
+        (oldest..oldest + self.stack_depth as usize).map(|index| self.stack[index % STACK_SIZE])
+    }
+
     pub fn is_sound_playing(&self) -> bool {
         self.sound_timer > 0
     }
 
+    /// Whether the program is busy-waiting on something that can only change
+    /// at the next timer tick or key event, so the remaining cycles of the
+    /// current frame can be skipped without changing what the program sees.
+    pub fn is_idle(&self) -> bool {
+        let pc = self.program_counter;
+        let instruction = Instruction::read(&self.memory, pc);
+
+        match instruction {
+            Instruction(0x1, _, _, _) if instruction.nnn() == pc => {
+                // 1nnn - JP to itself
+                true
+            }
+            Instruction(0xF, _, 0x0, 0xA) => {
+                // Fx0A - LD Vx, K with no key held
+                !(0x0..=0xF).any(|key| self.keypad.get_key(key))
+            }
+            Instruction(0xF, x, 0x0, 0x7) => {
+                // Fx07 - LD Vx, DT followed by a skip and a jump back to the Fx07
+                let skip = Instruction::read(&self.memory, pc.wrapping_add(2));
+                let jump = Instruction::read(&self.memory, pc.wrapping_add(4));
+
+                if jump.0 != 0x1 || jump.nnn() != pc || skip.x() != x {
+                    return false;
+                }
+
+                match skip {
+                    // 3xkk - loops until DT == kk
+                    Instruction(0x3, _, _, _) => self.delay_timer != skip.kk(),
+                    // 4xkk - loops until DT != kk
+                    Instruction(0x4, _, _, _) => self.delay_timer == skip.kk(),
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// The register `8xy6`/`8xyE` shift, depending on the quirks.
+    fn shift_source(&self, instruction: &Instruction) -> usize {
+        if self.quirks.shift_vy {
+            instruction.y() as usize
+        } else {
+            instruction.x() as usize
+        }
+    }
+
     pub fn execute_instruction_cycle(&mut self) {
+        self.execute_instruction_cycle_with(&mut ());
+    }
+
+    pub fn execute_instruction_cycle_with<O: Observer + ?Sized>(&mut self, observer: &mut O) {
         let instruction = Instruction::read(&self.memory, self.program_counter);
-        self.execute_instruction(instruction);
+        self.execute_instruction_with(instruction, observer);
+        self.frame_cycle += 1;
     }
 
+    #[cfg(test)]
     fn execute_instruction(&mut self, instruction: Instruction) {
-        self.program_counter += 2;
+        self.execute_instruction_with(instruction, &mut ());
+    }
+
+    fn execute_instruction_with<O: Observer + ?Sized>(
+        &mut self,
+        instruction: Instruction,
+        observer: &mut O,
+    ) {
+        let address = self.program_counter;
+        observer.on_instruction(address, &instruction);
+        self.program_counter = self.program_counter.wrapping_add(2);
 
         match instruction {
             Instruction(0x0, 0x0, 0xE, 0x0) => {
                 // 00E0 - CLS
-                self.display.cls()
+                self.display.cls();
+                observer.on_clear_screen();
             }
             Instruction(0x0, 0x0, 0xE, 0xE) => {
                 // 00EE - RET
-                self.stack_pointer -= 1;
+                // The stack is a ring, so stray returns and runaway recursion
+                // read and overwrite old entries instead of going out of
+                // bounds.
+                self.stack_pointer = (self.stack_pointer + STACK_SIZE as u8 - 1) % STACK_SIZE as u8;
+                self.stack_depth = self.stack_depth.saturating_sub(1);
                 self.program_counter = self.stack[self.stack_pointer as usize];
+                observer.on_return(address, self.program_counter);
             }
             Instruction(0x0, _, _, _) => {
                 // 0nnn - SYS addr
@@ -116,8 +434,10 @@ impl Emulator {
             Instruction(0x2, _, _, _) => {
                 // 2nnn - CALL addr
                 self.stack[self.stack_pointer as usize] = self.program_counter;
-                self.stack_pointer += 1;
+                self.stack_pointer = (self.stack_pointer + 1) % STACK_SIZE as u8;
+                self.stack_depth = (self.stack_depth + 1).min(STACK_SIZE as u8);
                 self.program_counter = instruction.nnn();
+                observer.on_call(address, self.program_counter);
             }
             Instruction(0x3, _, _, _) => {
                 // 3xkk - SE Vx, byte
@@ -125,7 +445,7 @@ impl Emulator {
                 let kk = instruction.kk();
 
                 if vx == kk {
-                    self.program_counter += 2;
+                    self.program_counter = self.program_counter.wrapping_add(2);
                 }
             }
             Instruction(0x4, _, _, _) => {
@@ -134,7 +454,7 @@ impl Emulator {
                 let kk = instruction.kk();
 
                 if vx != kk {
-                    self.program_counter += 2;
+                    self.program_counter = self.program_counter.wrapping_add(2);
                 }
             }
             Instruction(0x5, _, _, 0x0) => {
@@ -143,7 +463,7 @@ impl Emulator {
                 let vy = self.v_registers[instruction.y() as usize];
 
                 if vx == vy {
-                    self.program_counter += 2;
+                    self.program_counter = self.program_counter.wrapping_add(2);
                 }
             }
             Instruction(0x6, _, _, _) => {
@@ -165,18 +485,27 @@ impl Emulator {
                 self.v_registers[instruction.x() as usize] = self.v_registers
                     [instruction.x() as usize]
                     | self.v_registers[instruction.y() as usize];
+                if self.quirks.logic_resets_vf {
+                    self.v_registers[0xF] = 0;
+                }
             }
             Instruction(0x8, _, _, 0x2) => {
                 // 8xy2 - AND Vx, Vy
                 self.v_registers[instruction.x() as usize] = self.v_registers
                     [instruction.x() as usize]
                     & self.v_registers[instruction.y() as usize];
+                if self.quirks.logic_resets_vf {
+                    self.v_registers[0xF] = 0;
+                }
             }
             Instruction(0x8, _, _, 0x3) => {
                 // 8xy3 - XOR Vx, Vy
                 self.v_registers[instruction.x() as usize] = self.v_registers
                     [instruction.x() as usize]
                     ^ self.v_registers[instruction.y() as usize];
+                if self.quirks.logic_resets_vf {
+                    self.v_registers[0xF] = 0;
+                }
             }
             Instruction(0x8, _, _, 0x4) => {
                 // 8xy4 - ADD Vx, Vy
@@ -196,8 +525,7 @@ impl Emulator {
             }
             Instruction(0x8, _, _, 0x6) => {
                 // 8xy6 - SHR Vx {, Vy}
-
-                let vx = self.v_registers[instruction.x() as usize];
+                let vx = self.v_registers[self.shift_source(&instruction)];
                 let (vx, overflow) = (vx / 2, vx % 2);
 
                 self.v_registers[instruction.x() as usize] = vx;
@@ -213,7 +541,8 @@ impl Emulator {
             }
             Instruction(0x8, _, _, 0xE) => {
                 // 8xyE - SHL Vx {, Vy}
-                let (vx, overflow) = self.v_registers[instruction.x() as usize].overflowing_mul(2);
+                let (vx, overflow) =
+                    self.v_registers[self.shift_source(&instruction)].overflowing_mul(2);
 
                 self.v_registers[instruction.x() as usize] = vx;
                 self.v_registers[0xF] = if overflow { 0x1 } else { 0x0 };
@@ -224,7 +553,7 @@ impl Emulator {
                 let vy = self.v_registers[instruction.y() as usize];
 
                 if vx != vy {
-                    self.program_counter += 2;
+                    self.program_counter = self.program_counter.wrapping_add(2);
                 }
             }
             Instruction(0xA, _, _, _) => {
@@ -233,44 +562,66 @@ impl Emulator {
             }
             Instruction(0xB, _, _, _) => {
                 // Bnnn - JP V0, addr
-                self.program_counter = instruction.nnn().wrapping_add(self.v_registers[0x0] as u16);
+                let offset = if self.quirks.jump_vx {
+                    self.v_registers[instruction.x() as usize]
+                } else {
+                    self.v_registers[0x0]
+                };
+                self.program_counter = instruction.nnn().wrapping_add(offset as u16);
             }
             Instruction(0xC, _, _, _) => {
                 // Cxkk - RND Vx, byte
-                self.v_registers[instruction.x() as usize] =
-                    rand::thread_rng().gen::<u8>() & instruction.kk();
+                self.v_registers[instruction.x() as usize] = self.rng.next_u8() & instruction.kk();
             }
             Instruction(0xD, _, _, _) => {
                 // Dxyn - DRW Vx, Vy, nibble
                 let x = instruction.x() as usize;
                 let y = instruction.y() as usize;
-                let mem_start = self.i_register as usize;
-                let mem_end = mem_start + instruction.n() as usize;
+                let n = instruction.n() as usize;
+
+                let mut sprite = [0; 0x10];
+                for (offset, row) in sprite[..n].iter_mut().enumerate() {
+                    let address = self.i_register.wrapping_add(offset as u16);
+                    *row = self.memory.read(address);
+                    observer.on_memory_read(address, *row);
+                }
 
-                let collision = self.display.draw(
+                let draw = if self.quirks.clip_sprites {
+                    Display::draw_clipped
+                } else {
+                    Display::draw
+                };
+                let collision = draw(
+                    &mut self.display,
                     self.v_registers[x] as usize,
                     self.v_registers[y] as usize,
-                    &self.memory[mem_start..mem_end],
+                    &sprite[..n],
                 );
 
                 self.v_registers[0xF] = if collision { 0x1 } else { 0x0 };
+                observer.on_draw(
+                    self.v_registers[x],
+                    self.v_registers[y],
+                    &sprite[..n],
+                    collision,
+                );
             }
             Instruction(0xE, _, 0x9, 0xE) => {
                 // Ex9E - SKP Vx
                 let key_pressed = self
                     .keypad
-                    .get_key(self.v_registers[instruction.x() as usize]);
+                    .get_key(self.v_registers[instruction.x() as usize] & 0xF);
                 if key_pressed {
-                    self.program_counter += 2;
+                    self.program_counter = self.program_counter.wrapping_add(2);
                 }
             }
             Instruction(0xE, _, 0xA, 0x1) => {
                 // ExA1 - SKNP Vx
                 let key_pressed = self
                     .keypad
-                    .get_key(self.v_registers[instruction.x() as usize]);
+                    .get_key(self.v_registers[instruction.x() as usize] & 0xF);
                 if !key_pressed {
-                    self.program_counter += 2;
+                    self.program_counter = self.program_counter.wrapping_add(2);
                 }
             }
             Instruction(0xF, _, 0x0, 0x7) => {
@@ -279,12 +630,22 @@ impl Emulator {
             }
             Instruction(0xF, _, 0x0, 0xA) => {
                 // Fx0A - LD Vx, K
-                self.program_counter -= 2;
-
-                for key in 0x0..=0xF {
-                    if self.keypad.get_key(key) {
+                match (0x0..=0xF).find(|&key| self.keypad.get_key(key)) {
+                    Some(key) => {
                         self.v_registers[instruction.x() as usize] = key;
-                        self.program_counter += 2;
+
+                        if self.waiting_for_key {
+                            self.waiting_for_key = false;
+                            observer.on_key_wait_end(key);
+                        }
+                    }
+                    None => {
+                        self.program_counter = address;
+
+                        if !self.waiting_for_key {
+                            self.waiting_for_key = true;
+                            observer.on_key_wait_begin(instruction.x());
+                        }
                     }
                 }
             }
@@ -294,7 +655,17 @@ impl Emulator {
             }
             Instruction(0xF, _, 0x1, 0x8) => {
                 // Fx18 - LD ST, Vx
+                let was_playing = self.is_sound_playing();
                 self.sound_timer = self.v_registers[instruction.x() as usize];
+
+                if was_playing != self.is_sound_playing() {
+                    if was_playing {
+                        observer.on_sound_stop();
+                    } else {
+                        observer.on_sound_start();
+                    }
+                    self.frame_sound.toggle(self.frame_cycle);
+                }
             }
             Instruction(0xF, _, 0x1, 0xE) => {
                 // Fx1E - ADD I, Vx
@@ -305,36 +676,79 @@ impl Emulator {
             Instruction(0xF, _, 0x2, 0x9) => {
                 // Fx29 - LD F, Vx
 
-                // Font sprites kept at 0..80
-                // 0 => 0..5, 1 => 5..10, 2 => 10..15, and so on.
-                self.i_register = self.v_registers[instruction.x() as usize] as u16 * 5;
+                // Font sprites kept at the small font address
+                // 0 => +0..5, 1 => +5..10, 2 => +10..15, and so on.
+                self.i_register = self
+                    .font
+                    .small_glyph_address(self.v_registers[instruction.x() as usize]);
+            }
+            Instruction(0xF, _, 0x3, 0x0) => {
+                // Fx30 - LD HF, Vx
+
+                // 8x10 font sprites, 10 bytes per digit.
+                self.i_register = self
+                    .font
+                    .large_glyph_address(self.v_registers[instruction.x() as usize]);
             }
             Instruction(0xF, _, 0x3, 0x3) => {
                 // Fx33 - LD B, Vx
                 let vx = self.v_registers[instruction.x() as usize];
-
-                self.memory[self.i_register as usize] = vx.div_euclid(100).rem_euclid(10);
-                self.memory[self.i_register as usize + 1] = vx.div_euclid(10).rem_euclid(10);
-                self.memory[self.i_register as usize + 2] = vx.rem_euclid(10);
+                let digits = [
+                    vx.div_euclid(100).rem_euclid(10),
+                    vx.div_euclid(10).rem_euclid(10),
+                    vx.rem_euclid(10),
+                ];
+
+                for (offset, digit) in digits.into_iter().enumerate() {
+                    let address = self.i_register.wrapping_add(offset as u16);
+                    if self.memory.write(address, digit) {
+                        observer.on_memory_write(address, digit);
+                    }
+                }
             }
             Instruction(0xF, _, 0x5, 0x5) => {
                 // Fx55 - LD [I], Vx
                 let x = instruction.x() as usize;
-                let mem_start = self.i_register as usize;
-                let mem_end = mem_start + x;
 
-                self.memory[mem_start..=mem_end].copy_from_slice(&self.v_registers[0..=x]);
+                for (offset, &value) in self.v_registers[0..=x].iter().enumerate() {
+                    let address = self.i_register.wrapping_add(offset as u16);
+                    if self.memory.write(address, value) {
+                        observer.on_memory_write(address, value);
+                    }
+                }
+                if self.quirks.load_store_increments_i {
+                    self.i_register = self.i_register.wrapping_add(x as u16 + 1);
+                }
             }
             Instruction(0xF, _, 0x6, 0x5) => {
                 // Fx65 - LD Vx, [I]
                 let x = instruction.x() as usize;
-                let mem_start = self.i_register as usize;
-                let mem_end = mem_start + x;
 
-                self.v_registers[0..=x].copy_from_slice(&self.memory[mem_start..=mem_end]);
+                for (offset, register) in self.v_registers[0..=x].iter_mut().enumerate() {
+                    let address = self.i_register.wrapping_add(offset as u16);
+                    *register = self.memory.read(address);
+                    observer.on_memory_read(address, *register);
+                }
+                if self.quirks.load_store_increments_i {
+                    self.i_register = self.i_register.wrapping_add(x as u16 + 1);
+                }
+            }
+            Instruction(0xF, _, 0x7, 0x5) => {
+                // Fx75 - LD R, Vx
+                let x = instruction.x() as usize;
+
+                self.rpl_flags[..=x].copy_from_slice(&self.v_registers[..=x]);
+                observer.on_save_flags(&self.rpl_flags);
+            }
+            Instruction(0xF, _, 0x8, 0x5) => {
+                // Fx85 - LD Vx, R
+                let x = instruction.x() as usize;
+
+                self.v_registers[..=x].copy_from_slice(&self.rpl_flags[..=x]);
             }
             _ => {
                 // Invalid Instruction
+                observer.on_unknown_opcode(address, &instruction);
             }
         }
     }
@@ -342,7 +756,12 @@ impl Emulator {
 
 #[cfg(test)]
 mod tests {
-    use crate::{display::DISPLAY_WIDTH, instruction::Instruction};
+    use crate::{
+        display::DISPLAY_WIDTH,
+        font::{Font, FontOverlapError, SCHIP_LARGE, VIP_SMALL},
+        instruction::Instruction,
+        Bus, Observer, Quirks, Ram, RomTooLargeError, STACK_SIZE,
+    };
 
     use super::Emulator;
 
@@ -601,6 +1020,56 @@ mod tests {
         );
     }
 
+    #[test]
+    fn quirk_jump_vx() {
+        let mut emulator = Emulator::new();
+        emulator.set_quirks(Quirks::SCHIP);
+        emulator.v_registers[0] = 0x12;
+        emulator.v_registers[1] = 0x01;
+
+        emulator.execute_instruction(Instruction::from_opcode(0xB123));
+        assert_eq!(emulator.program_counter, 0x0124, "jumped to xnn + Vx");
+    }
+
+    #[test]
+    fn quirk_shift_vy() {
+        let mut emulator = Emulator::new();
+        emulator.set_quirks(Quirks::VIP);
+        emulator.v_registers[0] = 0x00;
+        emulator.v_registers[1] = 0x81;
+
+        emulator.execute_instruction(Instruction::from_opcode(0x8016));
+        assert_eq!(emulator.v_registers[0], 0x40);
+        assert_eq!(emulator.v_registers[0xF], 0x1);
+
+        emulator.execute_instruction(Instruction::from_opcode(0x801E));
+        assert_eq!(emulator.v_registers[0], 0x02);
+        assert_eq!(emulator.v_registers[0xF], 0x1);
+    }
+
+    #[test]
+    fn quirk_logic_resets_vf() {
+        let mut emulator = Emulator::new();
+        emulator.set_quirks(Quirks::VIP);
+        emulator.v_registers[0xF] = 0x5;
+
+        emulator.execute_instruction(Instruction::from_opcode(0x8011));
+        assert_eq!(emulator.v_registers[0xF], 0x0);
+    }
+
+    #[test]
+    fn quirk_load_store_increments_i() {
+        let mut emulator = Emulator::new();
+        emulator.set_quirks(Quirks::VIP);
+        emulator.i_register = 0x300;
+
+        emulator.execute_instruction(Instruction::from_opcode(0xF255));
+        assert_eq!(emulator.i_register, 0x303);
+
+        emulator.execute_instruction(Instruction::from_opcode(0xF065));
+        assert_eq!(emulator.i_register, 0x304);
+    }
+
     #[test]
     fn opcode_rnd_vx_kk() {
         let mut emulator = Emulator::new();
@@ -769,31 +1238,78 @@ mod tests {
         emulator.execute_instruction(Instruction::from_opcode(0xF529));
 
         assert_eq!(
-            emulator.memory[emulator.i_register as usize], 0xF0,
+            emulator.memory.read(emulator.i_register),
+            0xF0,
             "first byte of digit"
         );
         assert_eq!(
-            emulator.memory[emulator.i_register as usize + 1],
+            emulator.memory.read(emulator.i_register + 1),
             0x10,
             "second byte of digit"
         );
         assert_eq!(
-            emulator.memory[emulator.i_register as usize + 2],
+            emulator.memory.read(emulator.i_register + 2),
             0xF0,
             "third byte of digit"
         );
         assert_eq!(
-            emulator.memory[emulator.i_register as usize + 3],
+            emulator.memory.read(emulator.i_register + 3),
             0x10,
             "fourth byte of digit"
         );
         assert_eq!(
-            emulator.memory[emulator.i_register as usize + 4],
+            emulator.memory.read(emulator.i_register + 4),
             0xF0,
             "fifth byte of digit"
         );
     }
 
+    #[test]
+    fn opcode_ld_hf_vx() {
+        let mut emulator = Emulator::new();
+        emulator.reset();
+
+        emulator.v_registers[5] = 0x08;
+
+        emulator.execute_instruction(Instruction::from_opcode(0xF530));
+
+        let i = emulator.i_register as usize;
+        assert_eq!(
+            emulator.memory.as_slice()[i..i + 10],
+            SCHIP_LARGE[80..90],
+            "big digit loaded at i"
+        );
+    }
+
+    #[test]
+    fn custom_font_placement() {
+        let mut emulator = Emulator::new();
+        let font = Font::new(VIP_SMALL, SCHIP_LARGE)
+            .with_small_address(0x100)
+            .unwrap();
+        emulator.set_font(font).unwrap();
+        emulator.reset();
+
+        emulator.v_registers[1] = 0x1;
+        emulator.execute_instruction(Instruction::from_opcode(0xF129));
+
+        assert_eq!(emulator.i_register, 0x105, "i points into the moved font");
+        assert_eq!(
+            emulator.memory.as_slice()[0x105..0x10A],
+            VIP_SMALL[5..10],
+            "the selected glyphs were loaded"
+        );
+    }
+
+    #[test]
+    fn overlapping_font_is_rejected() {
+        let mut emulator = Emulator::new();
+        let font = Font::default().with_small_address(0x0A0).unwrap();
+
+        assert_eq!(emulator.set_font(font), Err(FontOverlapError));
+        assert_eq!(emulator.font(), &Font::default(), "old font kept");
+    }
+
     #[test]
     fn opcode_ld_i_vx() {
         let mut emulator = Emulator::new();
@@ -806,26 +1322,78 @@ mod tests {
         // load v0 - v2 into memory at i
         emulator.execute_instruction(Instruction::from_opcode(0xF255));
         assert_eq!(
-            emulator.memory[emulator.i_register as usize], 5,
+            emulator.memory.read(emulator.i_register),
+            5,
             "V0 was loaded into memory at i"
         );
         assert_eq!(
-            emulator.memory[emulator.i_register as usize + 1],
+            emulator.memory.read(emulator.i_register + 1),
             4,
             "V1 was loaded into memory at i + 1"
         );
         assert_eq!(
-            emulator.memory[emulator.i_register as usize + 2],
+            emulator.memory.read(emulator.i_register + 2),
             3,
             "V2 was loaded into memory at i + 2"
         );
         assert_eq!(
-            emulator.memory[emulator.i_register as usize + 3],
+            emulator.memory.read(emulator.i_register + 3),
             0,
             "i + 3 was not loaded"
         );
     }
 
+    #[test]
+    fn protected_interpreter_area() {
+        let mut emulator =
+            Emulator::with_bus(Ram::<0x2000>::new().with_interpreter_protection(true));
+        emulator.reset();
+        emulator.v_registers[0] = 0xAA;
+
+        emulator.i_register = 0x000;
+        emulator.execute_instruction(Instruction::from_opcode(0xF055));
+        assert_eq!(emulator.memory.read(0x000), 0xF0, "font is untouched");
+
+        emulator.i_register = 0x1800;
+        emulator.execute_instruction(Instruction::from_opcode(0xF055));
+        assert_eq!(emulator.memory.read(0x1800), 0xAA, "the larger ram is used");
+    }
+
+    #[test]
+    fn rejected_writes_are_not_reported() {
+        let mut emulator =
+            Emulator::with_bus(Ram::<0x1000>::new().with_interpreter_protection(true));
+        let mut recorder = Recorder::default();
+        emulator.v_registers[0] = 0xAA;
+
+        emulator.i_register = 0x1FF;
+        emulator.execute_instruction_with(Instruction::from_opcode(0xF155), &mut recorder);
+
+        assert_eq!(recorder.events, ["write 200 0"]);
+    }
+
+    #[test]
+    fn load_rom_too_large() {
+        let mut emulator = Emulator::new();
+        emulator.reset();
+
+        assert_eq!(
+            emulator.load_rom(&[0xFF; 0xE01]),
+            Err(RomTooLargeError {
+                size: 0xE01,
+                max_size: 0xE00
+            })
+        );
+        assert_eq!(emulator.memory.read(0x000), 0xF0, "font is untouched");
+        assert_eq!(emulator.memory.read(0x200), 0x00, "nothing was loaded");
+
+        let mut emulator = Emulator::with_bus(Ram::<0x2000>::new());
+        assert!(
+            emulator.load_rom(&[0xFF; 0xE01]).is_ok(),
+            "fits a larger ram"
+        );
+    }
+
     #[test]
     fn opcode_ld_b_vx() {
         let mut emulator = Emulator::new();
@@ -834,23 +1402,19 @@ mod tests {
 
         // load v0 - v2 from memory at i
         emulator.execute_instruction(Instruction::from_opcode(0xF233));
-        assert_eq!(emulator.memory[emulator.i_register as usize], 2, "hundreds");
-        assert_eq!(emulator.memory[emulator.i_register as usize + 1], 3, "tens");
-        assert_eq!(
-            emulator.memory[emulator.i_register as usize + 2],
-            4,
-            "digits"
-        );
+        assert_eq!(emulator.memory.read(emulator.i_register), 2, "hundreds");
+        assert_eq!(emulator.memory.read(emulator.i_register + 1), 3, "tens");
+        assert_eq!(emulator.memory.read(emulator.i_register + 2), 4, "digits");
     }
 
     #[test]
     fn opcode_ld_vx_i() {
         let mut emulator = Emulator::new();
         emulator.i_register = 0x300;
-        emulator.memory[emulator.i_register as usize] = 5;
-        emulator.memory[emulator.i_register as usize + 1] = 4;
-        emulator.memory[emulator.i_register as usize + 2] = 3;
-        emulator.memory[emulator.i_register as usize + 3] = 2;
+        emulator.memory.write(emulator.i_register, 5);
+        emulator.memory.write(emulator.i_register + 1, 4);
+        emulator.memory.write(emulator.i_register + 2, 3);
+        emulator.memory.write(emulator.i_register + 3, 2);
 
         // load v0 - v2 from memory at i
         emulator.execute_instruction(Instruction::from_opcode(0xF265));
@@ -866,6 +1430,26 @@ mod tests {
         assert_eq!(emulator.v_registers[3], 0, "i + 3 was not loaded");
     }
 
+    #[test]
+    fn opcode_rpl_flags() {
+        let mut emulator = Emulator::new();
+        let mut recorder = Recorder::default();
+        emulator.v_registers[..3].copy_from_slice(&[7, 8, 9]);
+
+        // save v0 - v1 to the flags
+        emulator.execute_instruction_with(Instruction::from_opcode(0xF175), &mut recorder);
+        assert_eq!(emulator.rpl_flags()[..3], [7, 8, 0]);
+        assert_eq!(recorder.events, ["save flags 7 8"]);
+
+        emulator.reset();
+        assert_eq!(emulator.rpl_flags()[..2], [7, 8], "flags outlive a reset");
+
+        // load v0 - v2 from the flags
+        emulator.set_rpl_flags([1; 16]);
+        emulator.execute_instruction(Instruction::from_opcode(0xF285));
+        assert_eq!(emulator.v_registers[..4], [1, 1, 1, 0]);
+    }
+
     #[test]
     fn opcode_ret() {
         let mut emulator = Emulator::new();
@@ -887,6 +1471,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn stack_wraps_around() {
+        let mut emulator = Emulator::new();
+        emulator.program_counter = 0x200;
+
+        emulator.execute_instruction(Instruction::from_opcode(0x00EE));
+        assert_eq!(
+            emulator.stack_pointer as usize,
+            STACK_SIZE - 1,
+            "returning with an empty stack wraps"
+        );
+        assert_eq!(emulator.stack().count(), 0, "but the stack stays empty");
+
+        // Every call returns to just past the one before.
+        for call in 0..STACK_SIZE as u16 + 2 {
+            emulator.execute_instruction(Instruction::from_opcode(0x2300 + call * 2));
+        }
+        assert_eq!(emulator.stack_pointer, 1, "the 17th call wraps");
+        assert!(
+            emulator
+                .stack()
+                .eq((2..STACK_SIZE as u16 + 2).map(|call| 0x300 + call * 2)),
+            "only the latest calls are left, oldest first"
+        );
+    }
+
+    #[test]
+    fn skp_uses_low_nibble() {
+        let mut emulator = Emulator::new();
+        emulator.program_counter = 0x200;
+        emulator.v_registers[0] = 0x13;
+        emulator.keypad.key_down(0x3);
+
+        emulator.execute_instruction(Instruction::from_opcode(0xE09E));
+        assert_eq!(emulator.program_counter, 0x204);
+    }
+
     #[test]
     fn opcode_ld_i_addr() {
         let mut emulator = Emulator::new();
@@ -925,6 +1546,231 @@ mod tests {
         );
     }
 
+    #[test]
+    fn idle_jump_to_self() {
+        let mut emulator = Emulator::new();
+        emulator.reset();
+        emulator.load_rom(&[0x12, 0x00]).unwrap();
+
+        assert!(emulator.is_idle(), "jump to itself is idle");
+
+        emulator.load_rom(&[0x12, 0x02]).unwrap();
+        assert!(!emulator.is_idle(), "jump elsewhere is not idle");
+    }
+
+    #[test]
+    fn idle_delay_timer_poll() {
+        let mut emulator = Emulator::new();
+        emulator.reset();
+        // LD V3, DT; SE V3, 0; JP 0x200
+        emulator
+            .load_rom(&[0xF3, 0x07, 0x33, 0x00, 0x12, 0x00])
+            .unwrap();
+
+        emulator.delay_timer = 5;
+        assert!(emulator.is_idle(), "waiting for the delay timer");
+
+        emulator.delay_timer = 0;
+        assert!(!emulator.is_idle(), "loop exits on this iteration");
+
+        // LD V3, DT; SE V4, 0; JP 0x200
+        emulator
+            .load_rom(&[0xF3, 0x07, 0x34, 0x00, 0x12, 0x00])
+            .unwrap();
+        emulator.delay_timer = 5;
+        assert!(!emulator.is_idle(), "skip tests a different register");
+    }
+
+    #[test]
+    fn idle_key_wait() {
+        let mut emulator = Emulator::new();
+        emulator.reset();
+        emulator.load_rom(&[0xF1, 0x0A]).unwrap();
+
+        assert!(emulator.is_idle(), "waiting for a key");
+
+        emulator.keypad.key_down(0x4);
+        assert!(!emulator.is_idle(), "key is held");
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+    }
+
+    impl Observer for Recorder {
+        fn on_clear_screen(&mut self) {
+            self.events.push("cls".to_string());
+        }
+
+        fn on_draw(&mut self, x: u8, y: u8, sprite: &[u8], collision: bool) {
+            self.events
+                .push(format!("draw {x} {y} {} {collision}", sprite.len()));
+        }
+
+        fn on_sound_start(&mut self) {
+            self.events.push("sound start".to_string());
+        }
+
+        fn on_sound_stop(&mut self) {
+            self.events.push("sound stop".to_string());
+        }
+
+        fn on_key_wait_begin(&mut self, register: u8) {
+            self.events.push(format!("key wait V{register:X}"));
+        }
+
+        fn on_key_wait_end(&mut self, key: u8) {
+            self.events.push(format!("key {key:X}"));
+        }
+
+        fn on_memory_read(&mut self, address: u16, value: u8) {
+            self.events.push(format!("read {address:03X} {value}"));
+        }
+
+        fn on_memory_write(&mut self, address: u16, value: u8) {
+            self.events.push(format!("write {address:03X} {value}"));
+        }
+
+        fn on_call(&mut self, from: u16, to: u16) {
+            self.events.push(format!("call {from:03X} {to:03X}"));
+        }
+
+        fn on_return(&mut self, from: u16, to: u16) {
+            self.events.push(format!("ret {from:03X} {to:03X}"));
+        }
+
+        fn on_unknown_opcode(&mut self, address: u16, instruction: &Instruction) {
+            self.events
+                .push(format!("unknown {address:03X} {:X}", instruction.0));
+        }
+
+        fn on_save_flags(&mut self, flags: &[u8; 16]) {
+            self.events
+                .push(format!("save flags {} {}", flags[0], flags[1]));
+        }
+    }
+
+    #[test]
+    fn observer_events() {
+        let mut emulator = Emulator::new();
+        let mut recorder = Recorder::default();
+        emulator.reset();
+        emulator
+            .load_rom(&[
+                0x22, 0x10, // 200: CALL 210
+                0xF1, 0x0A, // 202: LD V1, K
+                0xF1, 0x18, // 204: LD ST, V1
+                0xF0, 0x55, // 206: LD [I], V0
+                0x00, 0xE0, // 208: CLS
+                0x5F, 0xFF, // 20A: invalid
+                0x00, 0x00, // 20C
+                0x00, 0x00, // 20E
+                0x60, 0x02, // 210: LD V0, 2
+                0xA3, 0x00, // 212: LD I, 300
+                0xD0, 0x01, // 214: DRW V0, V0, 1
+                0x00, 0xEE, // 216: RET
+            ])
+            .unwrap();
+
+        for _ in 0..6 {
+            emulator.execute_instruction_cycle_with(&mut recorder);
+        }
+        emulator.keypad.key_down(0x1);
+        for _ in 0..5 {
+            emulator.execute_instruction_cycle_with(&mut recorder);
+        }
+        emulator.decrement_timers_with(&mut recorder);
+
+        assert_eq!(
+            recorder.events,
+            [
+                "call 200 210",
+                "read 300 0",
+                "draw 2 2 1 false",
+                "ret 216 202",
+                "key wait V1",
+                "key 1",
+                "sound start",
+                "write 300 2",
+                "cls",
+                "unknown 20A 5",
+                "sound stop",
+            ]
+        );
+    }
+
+    #[test]
+    fn frame_sound() {
+        let mut emulator = Emulator::new();
+        emulator.reset();
+        emulator
+            .load_rom(&[
+                0x60, 0x01, // LD V0, 1
+                0x61, 0x00, // LD V1, 0
+                0xF0, 0x18, // LD ST, V0
+                0xF1, 0x18, // LD ST, V1
+                0xF0, 0x18, // LD ST, V0
+                0x12, 0x0A, // JP 20A
+            ])
+            .unwrap();
+
+        for _ in 0..10 {
+            emulator.execute_instruction_cycle();
+        }
+        emulator.decrement_timers();
+
+        let sound = emulator.last_frame_sound();
+        assert!(!sound.is_on_at_start());
+        assert!(!sound.is_on_at(1));
+        assert!(sound.is_on_at(2));
+        assert!(!sound.is_on_at(3));
+        assert!(sound.is_on_at(9));
+
+        for _ in 0..10 {
+            emulator.execute_instruction_cycle();
+        }
+        emulator.decrement_timers();
+        assert!(
+            emulator.last_frame_sound().is_silent(),
+            "the timer ran out at the end of the first frame"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let mut emulator = Emulator::new();
+        emulator.reset();
+        emulator.set_rng_seed(42);
+        // LD I, 0; RND V0, FF; DRW V0, V0, 5; JP 0x202
+        emulator
+            .load_rom(&[0xA0, 0x00, 0xC0, 0xFF, 0xD0, 0x05, 0x12, 0x02])
+            .unwrap();
+        emulator.keypad.key_down(0x3);
+
+        for _ in 0..10 {
+            emulator.execute_instruction_cycle();
+        }
+
+        let json = serde_json::to_string(&emulator).unwrap();
+        let mut restored: Emulator = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+
+        for _ in 0..10 {
+            emulator.execute_instruction_cycle();
+            restored.execute_instruction_cycle();
+        }
+
+        assert_eq!(
+            emulator.display.get_buffer(),
+            restored.display.get_buffer(),
+            "the restored emulator continues identically"
+        );
+        assert!(restored.keypad.get_key(0x3), "keypad state is kept");
+    }
+
     #[test]
     fn opcode_dxyn() {
         let mut emulator = Emulator::new();
@@ -0,0 +1,274 @@
+use std::io::{self, BufRead, Write};
+
+use crate::keypad::Keypad;
+use crate::quirks::Quirks;
+use crate::rom_hash::RomHash;
+
+const MAGIC: &str = "chip8-movie 1";
+
+/// An hour at 60 frames per second, far more than any real recording but
+/// small enough to keep in memory, so a corrupt run length can't exhaust it.
+pub const MAX_MOVIE_FRAMES: usize = 60 * 60 * 60;
+
+/// A recorded session: everything needed to replay a run exactly, namely the
+/// ROM it was made with, the RNG seed, the number of instruction cycles per
+/// frame, the quirks and the keypad state at the start of every frame.
+///
+/// On disk it's a small text file; frames are run length encoded as
+/// `<keypad state in hex> <frame count>` lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Movie {
+    pub rom_hash: RomHash,
+    pub seed: u64,
+    pub cycles_per_frame: u32,
+    pub quirks: Quirks,
+    pub frames: Vec<u16>,
+}
+
+impl Movie {
+    pub fn matches(&self, rom: &[u8]) -> bool {
+        self.rom_hash == RomHash::of(rom)
+    }
+
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "{MAGIC}")?;
+        writeln!(writer, "rom {}", self.rom_hash)?;
+        writeln!(writer, "seed {}", self.seed)?;
+        writeln!(writer, "cycles {}", self.cycles_per_frame)?;
+        writeln!(writer, "quirks {}", self.quirks)?;
+
+        let mut frames = self.frames.iter().peekable();
+        while let Some(&state) = frames.next() {
+            let mut count = 1;
+            while frames.next_if_eq(&&state).is_some() {
+                count += 1;
+            }
+            writeln!(writer, "{state:04x} {count}")?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read_from(reader: impl BufRead) -> io::Result<Self> {
+        let mut lines = reader.lines();
+        let mut header = |name: &str| -> io::Result<String> {
+            let line = lines
+                .next()
+                .ok_or_else(|| invalid("unexpected end of movie"))??;
+
+            line.strip_prefix(name)
+                .and_then(|value| value.strip_prefix(' '))
+                .map(str::to_owned)
+                .ok_or_else(|| invalid(&format!("expected `{name}` line")))
+        };
+
+        if header("chip8-movie")? != "1" {
+            return Err(invalid("unsupported movie version"));
+        }
+        let rom_hash = header("rom")?
+            .parse()
+            .map_err(|_| invalid("invalid rom hash"))?;
+        let seed = header("seed")?
+            .parse()
+            .map_err(|_| invalid("invalid seed"))?;
+        let cycles_per_frame = header("cycles")?
+            .parse()
+            .map_err(|_| invalid("invalid cycle count"))?;
+        let quirks = header("quirks")?
+            .parse()
+            .map_err(|_| invalid("invalid quirks"))?;
+
+        let mut frames = Vec::new();
+        for line in lines {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+
+            let (state, count) = line
+                .split_once(' ')
+                .ok_or_else(|| invalid("expected `<state> <count>` frame line"))?;
+            let state =
+                u16::from_str_radix(state, 16).map_err(|_| invalid("invalid keypad state"))?;
+            let count: usize = count.parse().map_err(|_| invalid("invalid frame count"))?;
+            if count > MAX_MOVIE_FRAMES - frames.len() {
+                return Err(invalid("movie is too long"));
+            }
+
+            frames.extend(std::iter::repeat_n(state, count));
+        }
+
+        Ok(Self {
+            rom_hash,
+            seed,
+            cycles_per_frame,
+            quirks,
+            frames,
+        })
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Captures the keypad once per frame. Call `record_frame` right before the
+/// frame's instruction cycles run.
+#[derive(Debug, Clone)]
+pub struct MovieRecorder {
+    movie: Movie,
+}
+
+impl MovieRecorder {
+    pub fn new(rom: &[u8], seed: u64, cycles_per_frame: u32, quirks: Quirks) -> Self {
+        Self {
+            movie: Movie {
+                rom_hash: RomHash::of(rom),
+                seed,
+                cycles_per_frame,
+                quirks,
+                frames: Vec::new(),
+            },
+        }
+    }
+
+    pub fn record_frame(&mut self, keypad: &Keypad) {
+        self.movie.frames.push(keypad.state());
+    }
+
+    pub fn movie(&self) -> &Movie {
+        &self.movie
+    }
+
+    pub fn finish(self) -> Movie {
+        self.movie
+    }
+}
+
+/// Feeds a recorded movie back into the keypad, one frame at a time. The
+/// emulator must have been reset, seeded with `movie.seed`, set to
+/// `movie.quirks` and loaded with the same ROM before the first frame.
+#[derive(Debug, Clone)]
+pub struct MoviePlayer {
+    movie: Movie,
+    frame: usize,
+}
+
+impl MoviePlayer {
+    pub fn new(movie: Movie) -> Self {
+        Self { movie, frame: 0 }
+    }
+
+    pub fn movie(&self) -> &Movie {
+        &self.movie
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.movie.frames.len()
+    }
+
+    /// Applies the next frame's input, returning `false` once the movie has
+    /// run out of frames and leaving the keypad alone.
+    pub fn play_frame(&mut self, keypad: &mut Keypad) -> bool {
+        match self.movie.frames.get(self.frame) {
+            Some(&state) => {
+                keypad.set_state(state);
+                self.frame += 1;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Emulator;
+
+    // Waits for a key, draws a random byte at the key's position and loops.
+    const ROM: [u8; 12] = [
+        0xF1, 0x0A, // LD V1, K
+        0xC2, 0xFF, // RND V2, FF
+        0xA3, 0x00, // LD I, 300
+        0xF2, 0x55, // LD [I], V2
+        0xD1, 0x11, // DRW V1, V1, 1
+        0x12, 0x00, // JP 200
+    ];
+
+    fn run(emulator: &mut Emulator, frames: usize, mut input: impl FnMut(usize, &mut Keypad)) {
+        for frame in 0..frames {
+            input(frame, &mut emulator.keypad);
+            for _ in 0..10 {
+                emulator.execute_instruction_cycle();
+            }
+            emulator.decrement_timers();
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut recorder = MovieRecorder::new(&ROM, 7, 10, Quirks::SCHIP);
+        let mut keypad = Keypad::new();
+        for frame in 0..100 {
+            keypad.set_state(if frame % 30 < 10 { 1 << 4 } else { 0 });
+            recorder.record_frame(&keypad);
+        }
+        let movie = recorder.finish();
+
+        let mut file = Vec::new();
+        movie.write_to(&mut file).unwrap();
+
+        assert_eq!(Movie::read_from(&file[..]).unwrap(), movie);
+        assert!(movie.matches(&ROM));
+        assert!(!movie.matches(&ROM[..10]));
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(Movie::read_from(&b"not a movie\n"[..]).is_err());
+        assert!(Movie::read_from(&b"chip8-movie 1\nrom 00\n"[..]).is_err());
+
+        let header = format!("{MAGIC}\nrom {}\nseed 1\ncycles 10\n", RomHash::of(&ROM));
+        assert!(Movie::read_from(header.as_bytes()).is_err());
+        let bad_quirks = header.clone() + "quirks fast\n";
+        assert!(Movie::read_from(bad_quirks.as_bytes()).is_err());
+        let too_long = header + "quirks none\n0000 18446744073709551615\n";
+        let err = Movie::read_from(too_long.as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "movie is too long");
+    }
+
+    #[test]
+    fn playback_is_deterministic() {
+        let mut emulator = Emulator::new();
+        emulator.reset();
+        emulator.set_rng_seed(99);
+        emulator.load_rom(&ROM).unwrap();
+
+        emulator.set_quirks(Quirks::VIP);
+        let mut recorder = MovieRecorder::new(&ROM, 99, 10, Quirks::VIP);
+        run(&mut emulator, 120, |frame, keypad| {
+            keypad.set_state(1 << (frame / 8 % 16));
+            recorder.record_frame(keypad);
+        });
+
+        let mut player = MoviePlayer::new(recorder.finish());
+        let mut replay = Emulator::new();
+        replay.reset();
+        replay.set_rng_seed(player.movie().seed);
+        replay.set_quirks(player.movie().quirks);
+        replay.load_rom(&ROM).unwrap();
+        run(&mut replay, 120, |_, keypad| {
+            assert!(player.play_frame(keypad));
+        });
+
+        assert!(player.is_finished());
+        assert_eq!(replay.display.get_buffer(), emulator.display.get_buffer());
+        assert_eq!(
+            replay.memory().as_slice(),
+            emulator.memory().as_slice(),
+            "random numbers were replayed"
+        );
+    }
+}
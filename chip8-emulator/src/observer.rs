@@ -0,0 +1,203 @@
+use crate::instruction::Instruction;
+
+/// Hooks called by the `Emulator` while it executes. Every method has an
+/// empty default, so implementors only override the events they care about.
+///
+/// Observers are passed per call to `execute_instruction_cycle_with` and
+/// `decrement_timers_with`; the plain methods use `()` which ignores all
+/// events.
+pub trait Observer {
+    /// The instruction at `address` is about to execute.
+    fn on_instruction(&mut self, _address: u16, _instruction: &Instruction) {}
+
+    /// `00E0` cleared the display.
+    fn on_clear_screen(&mut self) {}
+
+    /// `Dxyn` drew `sprite`, one byte per row, at (`x`, `y`).
+    fn on_draw(&mut self, _x: u8, _y: u8, _sprite: &[u8], _collision: bool) {}
+
+    /// The sound timer went from zero to a non zero value.
+    fn on_sound_start(&mut self) {}
+
+    /// The sound timer reached zero, either by counting down or by `Fx18`.
+    fn on_sound_stop(&mut self) {}
+
+    /// `Fx0A` started waiting for a key to store in `Vx`.
+    fn on_key_wait_begin(&mut self, _register: u8) {}
+
+    /// `Fx0A` finished waiting after `key` was pressed.
+    fn on_key_wait_end(&mut self, _key: u8) {}
+
+    /// The program read `value` from `address` as data, e.g. a sprite row
+    /// for `Dxyn` or a register for `Fx65`. Instruction fetches are reported
+    /// by `on_instruction` instead.
+    fn on_memory_read(&mut self, _address: u16, _value: u8) {}
+
+    /// The program wrote `value` to `address`.
+    fn on_memory_write(&mut self, _address: u16, _value: u8) {}
+
+    /// `2nnn` at `from` called the subroutine at `to`.
+    fn on_call(&mut self, _from: u16, _to: u16) {}
+
+    /// `00EE` at `from` returned to `to`.
+    fn on_return(&mut self, _from: u16, _to: u16) {}
+
+    /// The instruction at `address` doesn't decode to a known opcode.
+    fn on_unknown_opcode(&mut self, _address: u16, _instruction: &Instruction) {}
+
+    /// `Fx75` changed the RPL flags, which the HP-48 kept after the program
+    /// quit. Hosts that want the same save them here and restore them with
+    /// `Emulator::set_rpl_flags` next time.
+    fn on_save_flags(&mut self, _flags: &[u8; 16]) {}
+}
+
+impl Observer for () {}
+
+/// Lets an observer be switched on and off without changing its type.
+impl<O: Observer> Observer for Option<O> {
+    fn on_instruction(&mut self, address: u16, instruction: &Instruction) {
+        if let Some(observer) = self {
+            observer.on_instruction(address, instruction);
+        }
+    }
+
+    fn on_clear_screen(&mut self) {
+        if let Some(observer) = self {
+            observer.on_clear_screen();
+        }
+    }
+
+    fn on_draw(&mut self, x: u8, y: u8, sprite: &[u8], collision: bool) {
+        if let Some(observer) = self {
+            observer.on_draw(x, y, sprite, collision);
+        }
+    }
+
+    fn on_sound_start(&mut self) {
+        if let Some(observer) = self {
+            observer.on_sound_start();
+        }
+    }
+
+    fn on_sound_stop(&mut self) {
+        if let Some(observer) = self {
+            observer.on_sound_stop();
+        }
+    }
+
+    fn on_key_wait_begin(&mut self, register: u8) {
+        if let Some(observer) = self {
+            observer.on_key_wait_begin(register);
+        }
+    }
+
+    fn on_key_wait_end(&mut self, key: u8) {
+        if let Some(observer) = self {
+            observer.on_key_wait_end(key);
+        }
+    }
+
+    fn on_memory_read(&mut self, address: u16, value: u8) {
+        if let Some(observer) = self {
+            observer.on_memory_read(address, value);
+        }
+    }
+
+    fn on_memory_write(&mut self, address: u16, value: u8) {
+        if let Some(observer) = self {
+            observer.on_memory_write(address, value);
+        }
+    }
+
+    fn on_call(&mut self, from: u16, to: u16) {
+        if let Some(observer) = self {
+            observer.on_call(from, to);
+        }
+    }
+
+    fn on_return(&mut self, from: u16, to: u16) {
+        if let Some(observer) = self {
+            observer.on_return(from, to);
+        }
+    }
+
+    fn on_unknown_opcode(&mut self, address: u16, instruction: &Instruction) {
+        if let Some(observer) = self {
+            observer.on_unknown_opcode(address, instruction);
+        }
+    }
+
+    fn on_save_flags(&mut self, flags: &[u8; 16]) {
+        if let Some(observer) = self {
+            observer.on_save_flags(flags);
+        }
+    }
+}
+
+/// Sends every event to both observers, first to first.
+impl<A: Observer, B: Observer> Observer for (A, B) {
+    fn on_instruction(&mut self, address: u16, instruction: &Instruction) {
+        self.0.on_instruction(address, instruction);
+        self.1.on_instruction(address, instruction);
+    }
+
+    fn on_clear_screen(&mut self) {
+        self.0.on_clear_screen();
+        self.1.on_clear_screen();
+    }
+
+    fn on_draw(&mut self, x: u8, y: u8, sprite: &[u8], collision: bool) {
+        self.0.on_draw(x, y, sprite, collision);
+        self.1.on_draw(x, y, sprite, collision);
+    }
+
+    fn on_sound_start(&mut self) {
+        self.0.on_sound_start();
+        self.1.on_sound_start();
+    }
+
+    fn on_sound_stop(&mut self) {
+        self.0.on_sound_stop();
+        self.1.on_sound_stop();
+    }
+
+    fn on_key_wait_begin(&mut self, register: u8) {
+        self.0.on_key_wait_begin(register);
+        self.1.on_key_wait_begin(register);
+    }
+
+    fn on_key_wait_end(&mut self, key: u8) {
+        self.0.on_key_wait_end(key);
+        self.1.on_key_wait_end(key);
+    }
+
+    fn on_memory_read(&mut self, address: u16, value: u8) {
+        self.0.on_memory_read(address, value);
+        self.1.on_memory_read(address, value);
+    }
+
+    fn on_memory_write(&mut self, address: u16, value: u8) {
+        self.0.on_memory_write(address, value);
+        self.1.on_memory_write(address, value);
+    }
+
+    fn on_call(&mut self, from: u16, to: u16) {
+        self.0.on_call(from, to);
+        self.1.on_call(from, to);
+    }
+
+    fn on_return(&mut self, from: u16, to: u16) {
+        self.0.on_return(from, to);
+        self.1.on_return(from, to);
+    }
+
+    fn on_unknown_opcode(&mut self, address: u16, instruction: &Instruction) {
+        self.0.on_unknown_opcode(address, instruction);
+        self.1.on_unknown_opcode(address, instruction);
+    }
+
+    fn on_save_flags(&mut self, flags: &[u8; 16]) {
+        self.0.on_save_flags(flags);
+        self.1.on_save_flags(flags);
+    }
+}
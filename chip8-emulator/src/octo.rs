@@ -0,0 +1,917 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::PROGRAM_START;
+
+/// Stops a macro that uses itself from expanding forever.
+const MAX_EXPANSIONS: usize = 0x10000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssembleError {
+    /// 1-based line of the offending token.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AssembleError {}
+
+/// Assembles [Octo](https://github.com/JohnEarnest/Octo) source into a ROM
+/// loaded at `0x200`, which starts with a jump to the `main` label.
+///
+/// Covers the statements, conditionals, loops, macros and the `:const`,
+/// `:calc`, `:alias`, `:org`, `:byte`, `:unpack` and `:call` directives.
+/// `:calc` works on integers rather than Octo's floating point numbers.
+/// `:stringmode`, `:next`, `:pointer` and `:assert` are reported as errors.
+pub fn assemble_octo(source: &str) -> Result<Vec<u8>, AssembleError> {
+    let tokens = source
+        .lines()
+        .enumerate()
+        .flat_map(|(index, line)| {
+            let code = line.split('#').next().unwrap_or_default();
+            code.split_whitespace().map(move |token| (token, index + 1))
+        })
+        .collect();
+
+    let mut assembler = Assembler {
+        tokens,
+        position: 0,
+        rom: Vec::new(),
+        here: PROGRAM_START,
+        labels: HashMap::new(),
+        consts: HashMap::new(),
+        aliases: HashMap::new(),
+        macros: HashMap::new(),
+        expansions: 0,
+        fixups: Vec::new(),
+        flow: Vec::new(),
+    };
+
+    assembler.fixup(Fixup::Address, "main", 1);
+    assembler.emit(0x1000);
+    while assembler.position < assembler.tokens.len() {
+        assembler.statement()?;
+    }
+    assembler.finish()
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Fixup {
+    /// The low 12 bits of an instruction.
+    Address,
+    /// The 16 bit word after `i := long`.
+    Long,
+    /// The low byte of `v0 := nibble << 4 | high`, from `:unpack`.
+    UnpackHigh(u8),
+    /// The low byte of `v1 := low`, from `:unpack`.
+    UnpackLow,
+}
+
+#[derive(Debug)]
+enum Flow {
+    /// Address of the jump to the `else` or `end`.
+    If(u16),
+    /// Address of the jump past the `end`.
+    Else(u16),
+    /// Start of the loop and the jumps out of it from `while`.
+    Loop(u16, Vec<u16>),
+}
+
+/// A macro's parameter names and the tokens to substitute them into.
+struct Macro<'a> {
+    params: Vec<&'a str>,
+    body: Vec<(&'a str, usize)>,
+}
+
+struct Assembler<'a> {
+    tokens: Vec<(&'a str, usize)>,
+    position: usize,
+    rom: Vec<u8>,
+    here: u16,
+    labels: HashMap<&'a str, u16>,
+    consts: HashMap<&'a str, i32>,
+    aliases: HashMap<&'a str, u8>,
+    macros: HashMap<&'a str, Macro<'a>>,
+    expansions: usize,
+    fixups: Vec<(u16, Fixup, &'a str, usize)>,
+    flow: Vec<Flow>,
+}
+
+impl<'a> Assembler<'a> {
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, AssembleError> {
+        let line = self
+            .tokens
+            .get(self.position.saturating_sub(1))
+            .map_or(1, |&(_, line)| line);
+        Err(AssembleError {
+            line,
+            message: message.into(),
+        })
+    }
+
+    fn next(&mut self) -> Result<&'a str, AssembleError> {
+        match self.tokens.get(self.position) {
+            Some(&(token, _)) => {
+                self.position += 1;
+                Ok(token)
+            }
+            None => self.error("unexpected end of source"),
+        }
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).map(|&(token, _)| token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), AssembleError> {
+        let token = self.next()?;
+        if token != expected {
+            return self.error(format!("expected `{expected}`, found `{token}`"));
+        }
+        Ok(())
+    }
+
+    fn byte(&mut self, byte: u8) {
+        let offset = self.here.wrapping_sub(PROGRAM_START) as usize;
+        if self.rom.len() <= offset {
+            self.rom.resize(offset + 1, 0);
+        }
+        self.rom[offset] = byte;
+        self.here = self.here.wrapping_add(1);
+    }
+
+    fn emit(&mut self, opcode: u16) {
+        self.byte((opcode >> 8) as u8);
+        self.byte(opcode as u8);
+    }
+
+    fn fixup(&mut self, kind: Fixup, label: &'a str, line: usize) {
+        self.fixups.push((self.here, kind, label, line));
+    }
+
+    fn patch(&mut self, address: u16, kind: Fixup, target: u16) -> Result<(), String> {
+        let offset = address.wrapping_sub(PROGRAM_START) as usize;
+        let (offset, byte_count, value) = match kind {
+            Fixup::Address if target > 0xFFF => {
+                return Err(format!("address {target:#X} is out of reach"))
+            }
+            Fixup::Address => {
+                let high = self.rom[offset] as u16 & 0xF0;
+                (offset, 2, high << 8 | target)
+            }
+            Fixup::Long => (offset + 2, 2, target),
+            Fixup::UnpackHigh(nibble) => (offset + 1, 1, (nibble as u16) << 4 | target >> 8),
+            Fixup::UnpackLow => (offset + 1, 1, target & 0xFF),
+        };
+        let bytes = value.to_be_bytes();
+        self.rom[offset..offset + byte_count].copy_from_slice(&bytes[2 - byte_count..]);
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, AssembleError> {
+        if let Some(flow) = self.flow.last() {
+            let open = match flow {
+                Flow::If(_) | Flow::Else(_) => "`begin` without `end`",
+                Flow::Loop(..) => "`loop` without `again`",
+            };
+            return self.error(open);
+        }
+
+        for (address, kind, label, line) in std::mem::take(&mut self.fixups) {
+            let Some(&target) = self.labels.get(label) else {
+                let message = match label {
+                    "main" => "missing a `main` label".to_owned(),
+                    _ => format!("undefined name `{label}`"),
+                };
+                return Err(AssembleError { line, message });
+            };
+            self.patch(address, kind, target)
+                .map_err(|message| AssembleError { line, message })?;
+        }
+
+        Ok(self.rom)
+    }
+
+    fn register(&mut self) -> Result<u8, AssembleError> {
+        let token = self.next()?;
+        match register(token).or_else(|| self.aliases.get(token).copied()) {
+            Some(register) => Ok(register),
+            None => self.error(format!("expected a register, found `{token}`")),
+        }
+    }
+
+    fn is_register(&self, token: &str) -> bool {
+        register(token).is_some() || self.aliases.contains_key(token)
+    }
+
+    /// A number or a name defined so far.
+    fn value(&mut self) -> Result<i32, AssembleError> {
+        let token = self.next()?;
+        if let Some(number) = number(token) {
+            return Ok(number);
+        }
+        if let Some(&value) = self.consts.get(token) {
+            return Ok(value);
+        }
+        match self.labels.get(token) {
+            Some(&address) => Ok(address as i32),
+            None => self.error(format!("undefined name `{token}`")),
+        }
+    }
+
+    fn immediate(&mut self) -> Result<u8, AssembleError> {
+        let value = self.value()?;
+        if !(-128..=255).contains(&value) {
+            return self.error(format!("{value} doesn't fit in a byte"));
+        }
+        Ok(value as u8)
+    }
+
+    fn nibble(&mut self) -> Result<u8, AssembleError> {
+        let value = self.value()?;
+        if !(0..=15).contains(&value) {
+            return self.error(format!("{value} doesn't fit in a nibble"));
+        }
+        Ok(value as u8)
+    }
+
+    /// Emits `opcode` with an address that may be defined further down.
+    fn address(&mut self, opcode: u16, kind: Fixup) -> Result<(), AssembleError> {
+        let token = self.next()?;
+        let line = self.tokens[self.position - 1].1;
+        let known = number(token)
+            .or_else(|| self.consts.get(token).copied())
+            .or_else(|| self.labels.get(token).map(|&address| address as i32));
+        match (known, kind) {
+            (Some(value), _) if !(0..=0xFFFF).contains(&value) => {
+                self.error(format!("{value} isn't an address"))
+            }
+            (Some(value), Fixup::Long) => {
+                self.emit(opcode);
+                self.emit(value as u16);
+                Ok(())
+            }
+            (Some(value), _) if value > 0xFFF => {
+                self.error(format!("address {value:#X} is out of reach"))
+            }
+            (Some(value), _) => {
+                self.emit(opcode | value as u16);
+                Ok(())
+            }
+            (None, _) => {
+                self.fixup(kind, token, line);
+                self.emit(opcode);
+                if let Fixup::Long = kind {
+                    self.emit(0);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// A placeholder jump for the innermost `if`/`else`/`loop` to patch.
+    fn forward_jump(&mut self) -> u16 {
+        let address = self.here;
+        self.emit(0x1000);
+        address
+    }
+
+    fn land(&mut self, jump: u16) -> Result<(), AssembleError> {
+        match self.patch(jump, Fixup::Address, self.here) {
+            Ok(()) => Ok(()),
+            Err(message) => self.error(message),
+        }
+    }
+
+    fn statement(&mut self) -> Result<(), AssembleError> {
+        let token = self.next()?;
+        match token {
+            ":" => {
+                let name = self.next()?;
+                if self.labels.insert(name, self.here).is_some() {
+                    return self.error(format!("`{name}` is defined twice"));
+                }
+            }
+            ":const" => {
+                let name = self.next()?;
+                let value = self.value()?;
+                self.consts.insert(name, value);
+            }
+            ":calc" => {
+                let name = self.next()?;
+                self.expect("{")?;
+                let value = self.calc()?;
+                self.expect("}")?;
+                self.consts.insert(name, value);
+            }
+            ":macro" => self.define_macro()?,
+            ":alias" => {
+                let name = self.next()?;
+                let register = self.register()?;
+                self.aliases.insert(name, register);
+            }
+            ":org" => {
+                let address = self.value()?;
+                if !(PROGRAM_START as i32..=0xFFFF).contains(&address) {
+                    return self.error(format!("cannot place code at {address:#X}"));
+                }
+                self.here = address as u16;
+            }
+            ":byte" => {
+                let byte = self.immediate()?;
+                self.byte(byte);
+            }
+            ":unpack" => {
+                let nibble = self.nibble()?;
+                let token = self.next()?;
+                let line = self.tokens[self.position - 1].1;
+                self.fixup(Fixup::UnpackHigh(nibble), token, line);
+                self.emit(0x6000);
+                self.fixup(Fixup::UnpackLow, token, line);
+                self.emit(0x6100);
+            }
+            ":call" => self.address(0x2000, Fixup::Address)?,
+            ":breakpoint" => {
+                self.next()?;
+            }
+            ":monitor" => {
+                self.next()?;
+                self.next()?;
+            }
+            "clear" => self.emit(0x00E0),
+            "return" | ";" => self.emit(0x00EE),
+            "exit" => self.emit(0x00FD),
+            "hires" => self.emit(0x00FF),
+            "lores" => self.emit(0x00FE),
+            "scroll-down" => {
+                let n = self.nibble()?;
+                self.emit(0x00C0 | n as u16);
+            }
+            "scroll-up" => {
+                let n = self.nibble()?;
+                self.emit(0x00D0 | n as u16);
+            }
+            "scroll-right" => self.emit(0x00FB),
+            "scroll-left" => self.emit(0x00FC),
+            "audio" => self.emit(0xF002),
+            "plane" => {
+                let n = self.nibble()?;
+                self.emit(0xF001 | (n as u16) << 8);
+            }
+            "jump" => self.address(0x1000, Fixup::Address)?,
+            "jump0" => self.address(0xB000, Fixup::Address)?,
+            "sprite" => {
+                let x = self.register()?;
+                let y = self.register()?;
+                let n = self.nibble()?;
+                self.emit(0xD000 | (x as u16) << 8 | (y as u16) << 4 | n as u16);
+            }
+            "bcd" => self.register_op(0xF033)?,
+            "saveflags" => self.register_op(0xF075)?,
+            "loadflags" => self.register_op(0xF085)?,
+            "save" | "load" => {
+                let x = self.register()?;
+                if self.peek() == Some("-") {
+                    self.next()?;
+                    let y = self.register()?;
+                    let low = if token == "save" { 0x2 } else { 0x3 };
+                    self.emit(0x5000 | (x as u16) << 8 | (y as u16) << 4 | low);
+                } else {
+                    let low = if token == "save" { 0x55 } else { 0x65 };
+                    self.emit(0xF000 | (x as u16) << 8 | low);
+                }
+            }
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let low = match token {
+                    "delay" => 0x15,
+                    "buzzer" => 0x18,
+                    _ => 0x3A,
+                };
+                self.register_op(0xF000 | low)?;
+            }
+            "i" => self.index()?,
+            "if" => {
+                let skip = self.condition()?;
+                match self.next()? {
+                    "then" => {
+                        for opcode in skip {
+                            self.emit(opcode);
+                        }
+                    }
+                    "begin" => {
+                        for opcode in invert(skip) {
+                            self.emit(opcode);
+                        }
+                        let jump = self.forward_jump();
+                        self.flow.push(Flow::If(jump));
+                    }
+                    other => {
+                        return self.error(format!("expected `then` or `begin`, found `{other}`"))
+                    }
+                }
+            }
+            "else" => {
+                let Some(Flow::If(jump)) = self.flow.pop() else {
+                    return self.error("`else` without `if ... begin`");
+                };
+                let end = self.forward_jump();
+                self.land(jump)?;
+                self.flow.push(Flow::Else(end));
+            }
+            "end" => match self.flow.pop() {
+                Some(Flow::If(jump) | Flow::Else(jump)) => self.land(jump)?,
+                _ => return self.error("`end` without `if ... begin`"),
+            },
+            "loop" => self.flow.push(Flow::Loop(self.here, Vec::new())),
+            "while" => {
+                let skip = self.condition()?;
+                for opcode in invert(skip) {
+                    self.emit(opcode);
+                }
+                let jump = self.forward_jump();
+                match self.flow.iter_mut().rev().find_map(|flow| match flow {
+                    Flow::Loop(_, exits) => Some(exits),
+                    _ => None,
+                }) {
+                    Some(exits) => exits.push(jump),
+                    None => return self.error("`while` outside of a loop"),
+                }
+            }
+            "again" => {
+                let Some(Flow::Loop(start, exits)) = self.flow.pop() else {
+                    return self.error("`again` without `loop`");
+                };
+                if start > 0xFFF {
+                    return self.error(format!("address {start:#X} is out of reach"));
+                }
+                self.emit(0x1000 | start);
+                for jump in exits {
+                    self.land(jump)?;
+                }
+            }
+            ":stringmode" | ":assert" | ":next" | ":pointer" => {
+                return self.error(format!("`{token}` is not supported"))
+            }
+            _ if self.macros.contains_key(token) => self.expand(token)?,
+            _ if self.is_register(token) => {
+                self.position -= 1;
+                self.register_statement()?;
+            }
+            _ if number(token).is_some() => {
+                self.position -= 1;
+                let byte = self.immediate()?;
+                self.byte(byte);
+            }
+            _ if token.starts_with(':') => {
+                return self.error(format!("unknown directive `{token}`"))
+            }
+            _ => {
+                // A bare name calls the subroutine of that name.
+                self.position -= 1;
+                self.address(0x2000, Fixup::Address)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `:macro name params... { body }`, kept as tokens to splice in
+    /// wherever it's used.
+    fn define_macro(&mut self) -> Result<(), AssembleError> {
+        let name = self.next()?;
+        let mut params = Vec::new();
+        loop {
+            match self.next()? {
+                "{" => break,
+                param => params.push(param),
+            }
+        }
+
+        let mut body = Vec::new();
+        let mut depth = 0;
+        loop {
+            match self.next()? {
+                "{" => depth += 1,
+                "}" if depth == 0 => break,
+                "}" => depth -= 1,
+                _ => {}
+            }
+            body.push(self.tokens[self.position - 1]);
+        }
+
+        self.macros.insert(name, Macro { params, body });
+        Ok(())
+    }
+
+    /// Replaces a use of macro `name` and its arguments with its body.
+    fn expand(&mut self, name: &str) -> Result<(), AssembleError> {
+        self.expansions += 1;
+        if self.expansions > MAX_EXPANSIONS {
+            return self.error(format!("`{name}` expands too many times"));
+        }
+
+        let mut args = Vec::new();
+        for _ in 0..self.macros[name].params.len() {
+            args.push(self.next()?);
+        }
+        let Macro { params, body } = &self.macros[name];
+        let expansion: Vec<_> = body
+            .iter()
+            .map(
+                |&(token, line)| match params.iter().position(|&param| param == token) {
+                    Some(index) => (args[index], line),
+                    None => (token, line),
+                },
+            )
+            .collect();
+        self.tokens.splice(self.position..self.position, expansion);
+        Ok(())
+    }
+
+    /// A `:calc` expression. As in Octo, binary operators all have the same
+    /// precedence and group to the right, so `2 * 3 + 1` is 8.
+    fn calc(&mut self) -> Result<i32, AssembleError> {
+        let left = self.calc_term()?;
+        let operator = match self.peek() {
+            Some("}" | ")") | None => return Ok(left),
+            Some(_) => self.next()?,
+        };
+        let right = self.calc()?;
+
+        let value = match operator {
+            "+" => left.checked_add(right),
+            "-" => left.checked_sub(right),
+            "*" => left.checked_mul(right),
+            "/" => left.checked_div(right),
+            "%" => left.checked_rem(right),
+            "&" => Some(left & right),
+            "|" => Some(left | right),
+            "^" => Some(left ^ right),
+            "<<" => u32::try_from(right).ok().and_then(|n| left.checked_shl(n)),
+            ">>" => u32::try_from(right).ok().and_then(|n| left.checked_shr(n)),
+            "min" => Some(left.min(right)),
+            "max" => Some(left.max(right)),
+            "<" => Some((left < right) as i32),
+            "<=" => Some((left <= right) as i32),
+            ">" => Some((left > right) as i32),
+            ">=" => Some((left >= right) as i32),
+            "==" => Some((left == right) as i32),
+            "!=" => Some((left != right) as i32),
+            _ => return self.error(format!("unknown operator `{operator}`")),
+        };
+        match value {
+            Some(value) => Ok(value),
+            None => self.error(format!("cannot calculate {left} {operator} {right}")),
+        }
+    }
+
+    fn calc_term(&mut self) -> Result<i32, AssembleError> {
+        match self.peek() {
+            Some("(") => {
+                self.next()?;
+                let value = self.calc()?;
+                self.expect(")")?;
+                Ok(value)
+            }
+            Some("-") => {
+                self.next()?;
+                Ok(self.calc_term()?.wrapping_neg())
+            }
+            Some("~") => {
+                self.next()?;
+                Ok(!self.calc_term()?)
+            }
+            Some("!") => {
+                self.next()?;
+                Ok((self.calc_term()? == 0) as i32)
+            }
+            Some("HERE") => {
+                self.next()?;
+                Ok(self.here as i32)
+            }
+            _ => self.value(),
+        }
+    }
+
+    /// `opcode` with a register in its `x` nibble.
+    fn register_op(&mut self, opcode: u16) -> Result<(), AssembleError> {
+        let x = self.register()?;
+        self.emit(opcode | (x as u16) << 8);
+        Ok(())
+    }
+
+    fn index(&mut self) -> Result<(), AssembleError> {
+        match self.next()? {
+            ":=" => match self.peek() {
+                Some("long") => {
+                    self.next()?;
+                    self.address(0xF000, Fixup::Long)
+                }
+                Some("hex") => {
+                    self.next()?;
+                    self.register_op(0xF029)
+                }
+                Some("bighex") => {
+                    self.next()?;
+                    self.register_op(0xF030)
+                }
+                _ => self.address(0xA000, Fixup::Address),
+            },
+            "+=" => self.register_op(0xF01E),
+            other => self.error(format!("unknown operator `{other}` for i")),
+        }
+    }
+
+    fn register_statement(&mut self) -> Result<(), AssembleError> {
+        let x = self.register()? as u16;
+        let operator = self.next()?;
+
+        let vy = self
+            .peek()
+            .filter(|&token| self.is_register(token))
+            .is_some();
+        let opcode = match (operator, vy) {
+            (":=", true) => 0x8000 | x << 8 | (self.register()? as u16) << 4,
+            (":=", false) => match self.peek() {
+                Some("random") => {
+                    self.next()?;
+                    0xC000 | x << 8 | self.immediate()? as u16
+                }
+                Some("delay") => {
+                    self.next()?;
+                    0xF007 | x << 8
+                }
+                Some("key") => {
+                    self.next()?;
+                    0xF00A | x << 8
+                }
+                _ => 0x6000 | x << 8 | self.immediate()? as u16,
+            },
+            ("+=", false) => 0x7000 | x << 8 | self.immediate()? as u16,
+            ("-=", false) => 0x7000 | x << 8 | self.immediate()?.wrapping_neg() as u16,
+            (_, true) => {
+                let low = match operator {
+                    "|=" => 0x1,
+                    "&=" => 0x2,
+                    "^=" => 0x3,
+                    "+=" => 0x4,
+                    "-=" => 0x5,
+                    ">>=" => 0x6,
+                    "=-" => 0x7,
+                    "<<=" => 0xE,
+                    _ => return self.error(format!("unknown operator `{operator}`")),
+                };
+                0x8000 | x << 8 | (self.register()? as u16) << 4 | low
+            }
+            (_, false) => return self.error(format!("`{operator}` needs a register")),
+        };
+        self.emit(opcode);
+        Ok(())
+    }
+
+    /// The instructions that skip the next one unless the condition holds.
+    fn condition(&mut self) -> Result<Vec<u16>, AssembleError> {
+        let x = self.register()? as u16;
+        let operator = self.next()?;
+        match operator {
+            "key" => return Ok(vec![0xE0A1 | x << 8]),
+            "-key" => return Ok(vec![0xE09E | x << 8]),
+            _ => {}
+        }
+
+        let y = self
+            .peek()
+            .filter(|&token| self.is_register(token))
+            .is_some();
+        let y = if y {
+            Operand::Register(self.register()? as u16)
+        } else {
+            Operand::Byte(self.immediate()? as u16)
+        };
+
+        // `<` and friends compare in VF: `vf -= vx` and `vf =- vx` leave 1
+        // in VF when no borrow was needed.
+        let load_vf = |operand| match operand {
+            Operand::Register(r) => 0x8F00 | r << 4,
+            Operand::Byte(n) => 0x6F00 | n,
+        };
+        let skip = match (operator, y) {
+            ("==", Operand::Register(y)) => vec![0x9000 | x << 8 | y << 4],
+            ("==", Operand::Byte(n)) => vec![0x4000 | x << 8 | n],
+            ("!=", Operand::Register(y)) => vec![0x5000 | x << 8 | y << 4],
+            ("!=", Operand::Byte(n)) => vec![0x3000 | x << 8 | n],
+            // VF = y >= x
+            ("<=", y) => vec![load_vf(y), 0x8F05 | x << 4, 0x4F01],
+            (">", y) => vec![load_vf(y), 0x8F05 | x << 4, 0x4F00],
+            // VF = x >= y
+            (">=", y) => vec![load_vf(y), 0x8F07 | x << 4, 0x4F01],
+            ("<", y) => vec![load_vf(y), 0x8F07 | x << 4, 0x4F00],
+            _ => return self.error(format!("unknown comparison `{operator}`")),
+        };
+        Ok(skip)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Operand {
+    Register(u16),
+    Byte(u16),
+}
+
+/// Turns the skip that ends a condition around, so it skips when the
+/// condition holds.
+fn invert(mut skip: Vec<u16>) -> Vec<u16> {
+    if let Some(last) = skip.last_mut() {
+        *last = match *last & 0xF00F {
+            0xE001 | 0xE00E => *last ^ 0x003F,
+            0x5000 | 0x9000 => *last ^ 0xC000,
+            _ => *last ^ 0x7000,
+        };
+    }
+    skip
+}
+
+fn register(token: &str) -> Option<u8> {
+    let digit = token.strip_prefix(['v', 'V'])?;
+    if digit.len() != 1 {
+        return None;
+    }
+    u8::from_str_radix(digit, 16).ok()
+}
+
+fn number(token: &str) -> Option<i32> {
+    let (negative, digits) = match token.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, token),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i32::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i32::from_str_radix(binary, 2).ok()?
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(rom: &[u8]) -> Vec<u16> {
+        rom.chunks(2)
+            .map(|word| u16::from_be_bytes([word[0], word[1]]))
+            .collect()
+    }
+
+    #[test]
+    fn statements() {
+        let source = "
+            :const speed 3
+            :alias ball v5
+            : main
+                clear
+                ball := speed  # a comment
+                ball += -1
+                v1 := ball
+                v1 >>= v1
+                i := sprite
+                sprite v0 v1 4
+                v2 := random 0xFF
+                delay := v2
+                save v3
+                loop again
+            : sprite
+                0b11110000 0x90
+        ";
+
+        let rom = assemble_octo(source).unwrap();
+
+        assert_eq!(
+            words(&rom),
+            [
+                0x1202, 0x00E0, 0x6503, 0x75FF, 0x8150, 0x8116, 0xA218, 0xD014, 0xC2FF, 0xF215,
+                0xF355, 0x1216, 0xF090,
+            ]
+        );
+    }
+
+    #[test]
+    fn conditions() {
+        let source = "
+            : main
+                if v0 == 3 then v1 := 1
+                if v0 != v2 then v1 := 2
+                if v3 key then v1 := 3
+                if v0 < 8 then v1 := 4
+                if v0 == 1 begin
+                    v1 := 5
+                else
+                    v1 := 6
+                end
+                loop
+                    v0 += 1
+                    while v0 != 10
+                again
+        ";
+
+        let rom = assemble_octo(source).unwrap();
+
+        assert_eq!(
+            words(&rom),
+            [
+                0x1202, // jump main
+                0x4003, 0x6101, // skip unless v0 == 3
+                0x5020, 0x6102, // skip unless v0 != v2
+                0xE3A1, 0x6103, // skip unless v3 is pressed
+                0x6F08, 0x8F07, 0x4F00, 0x6104, // VF = v0 >= 8, skip unless it's 0
+                0x3001, 0x121E, 0x6105, 0x1220, // if ... begin ... else
+                0x6106, // end
+                0x7001, 0x400A, 0x1228, 0x1220, // loop ... while ... again
+            ]
+        );
+    }
+
+    #[test]
+    fn forward_references() {
+        let source = "
+            : main
+                draw
+                i := long data
+                :unpack 0xA data
+                jump main
+            : draw ;
+            : data 1 2
+        ";
+
+        let rom = assemble_octo(source).unwrap();
+
+        assert_eq!(
+            words(&rom),
+            [0x1202, 0x220E, 0xF000, 0x0210, 0x60A2, 0x6110, 0x1202, 0x00EE, 0x0102]
+        );
+    }
+
+    #[test]
+    fn macros_and_calc() {
+        let source = "
+            :const WIDTH 64
+            :calc CENTER { ( WIDTH / 2 ) - 4 }
+            :calc GROUPED { ( 2 * 3 ) + 1 }
+            :calc RIGHT { 2 * 3 + 1 }
+            :macro move reg amount { reg += amount }
+            :macro twice what { what what }
+            : main
+                v0 := CENTER
+                v1 := GROUPED
+                v2 := RIGHT
+                move v0 2
+                twice clear
+            :calc END { HERE }
+                i := END
+        ";
+
+        let rom = assemble_octo(source).unwrap();
+
+        assert_eq!(
+            words(&rom),
+            [0x1202, 0x601C, 0x6107, 0x6208, 0x7002, 0x00E0, 0x00E0, 0xA20E]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let error = assemble_octo(": main\n  v0 := 5\n  v0 := 300\n").unwrap_err();
+        assert_eq!(error.line, 3);
+        assert_eq!(error.to_string(), "line 3: 300 doesn't fit in a byte");
+
+        let error = assemble_octo(": start\n  jump nowhere\n").unwrap_err();
+        assert_eq!(error.message, "missing a `main` label");
+
+        let error = assemble_octo(": main\n  jump nowhere\n").unwrap_err();
+        assert_eq!(
+            (error.line, error.message.as_str()),
+            (2, "undefined name `nowhere`")
+        );
+
+        let error = assemble_octo(": main\n  loop\n").unwrap_err();
+        assert_eq!(error.message, "`loop` without `again`");
+
+        let error = assemble_octo(":macro forever { forever }\n: main forever\n").unwrap_err();
+        assert_eq!(error.message, "`forever` expands too many times");
+
+        let error = assemble_octo(":calc oops { 1 / 0 }\n").unwrap_err();
+        assert_eq!(error.message, "cannot calculate 1 / 0");
+
+        let error = assemble_octo(": main\n  :stringmode\n").unwrap_err();
+        assert_eq!(error.message, "`:stringmode` is not supported");
+    }
+}
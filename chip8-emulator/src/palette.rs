@@ -0,0 +1,42 @@
+/// Colours used when turning the framebuffer into an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Palette {
+    pub off: [u8; 3],
+    pub on: [u8; 3],
+}
+
+impl Palette {
+    pub const fn new(off: [u8; 3], on: [u8; 3]) -> Self {
+        Self { off, on }
+    }
+
+    pub fn color(&self, pixel: bool) -> [u8; 3] {
+        if pixel {
+            self.on
+        } else {
+            self.off
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::new([0x00, 0x00, 0x00], [0xFF, 0xFF, 0xFF])
+    }
+}
+
+/// `#rrggbb`, as the ROM database and Octo write colours.
+#[cfg(feature = "database")]
+pub(crate) fn parse_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+
+    let mut rgb = [0; 3];
+    for (i, channel) in rgb.iter_mut().enumerate() {
+        *channel = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(rgb)
+}
@@ -0,0 +1,368 @@
+use std::io::{self, Read};
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+const BPS_MAGIC: &[u8] = b"BPS1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchFormat {
+    /// Offsets and replacement bytes, with run length encoded fills. Can't
+    /// tell which ROM it's meant for.
+    Ips,
+    /// Copies from the source ROM, the patch or the output so far, with
+    /// CRC-32 checksums of the source, the result and the patch itself.
+    Bps,
+}
+
+/// A fix or translation of a ROM, as distributed by the community.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+    format: PatchFormat,
+    bytes: Vec<u8>,
+}
+
+impl Patch {
+    /// Reads an IPS or BPS patch, telling them apart by their magic. A BPS
+    /// patch that doesn't match its own checksum is rejected here.
+    pub fn read_from(mut reader: impl Read) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        let format = if bytes.starts_with(IPS_MAGIC) {
+            PatchFormat::Ips
+        } else if bytes.starts_with(BPS_MAGIC) {
+            let (body, checksum) = bytes
+                .split_last_chunk::<4>()
+                .filter(|(body, _)| body.len() >= BPS_MAGIC.len() + 8)
+                .ok_or_else(|| invalid("patch is cut short"))?;
+            if crc32(body) != u32::from_le_bytes(*checksum) {
+                return Err(invalid("patch is corrupt"));
+            }
+            PatchFormat::Bps
+        } else {
+            return Err(invalid("not an IPS or BPS patch"));
+        };
+
+        Ok(Self { format, bytes })
+    }
+
+    pub fn format(&self) -> PatchFormat {
+        self.format
+    }
+
+    /// Returns the patched copy of `rom`. BPS patches check that `rom` is
+    /// the one they were made for, and that the result is what was intended.
+    pub fn apply(&self, rom: &[u8]) -> io::Result<Vec<u8>> {
+        match self.format {
+            PatchFormat::Ips => apply_ips(&self.bytes[IPS_MAGIC.len()..], rom),
+            PatchFormat::Bps => apply_bps(&self.bytes[BPS_MAGIC.len()..], rom),
+        }
+    }
+}
+
+/// Records are a 3 byte offset and a 2 byte length followed by that many
+/// bytes, or by a 2 byte count and a byte to repeat when the length is 0.
+/// `EOF` ends them, optionally followed by a 3 byte size to truncate to.
+fn apply_ips(mut patch: &[u8], rom: &[u8]) -> io::Result<Vec<u8>> {
+    let mut output = rom.to_vec();
+
+    loop {
+        let offset = take(&mut patch, 3)?;
+        if offset == IPS_EOF {
+            break;
+        }
+        let offset = be(offset);
+        let bytes = match be(take(&mut patch, 2)?) {
+            0 => {
+                let count = be(take(&mut patch, 2)?);
+                vec![take(&mut patch, 1)?[0]; count]
+            }
+            len => take(&mut patch, len)?.to_vec(),
+        };
+
+        let end = offset + bytes.len();
+        if output.len() < end {
+            output.resize(end, 0);
+        }
+        output[offset..end].copy_from_slice(&bytes);
+    }
+
+    if patch.len() >= 3 {
+        output.truncate(be(take(&mut patch, 3)?));
+    }
+
+    Ok(output)
+}
+
+/// After the sizes and the metadata come actions up to the three checksums
+/// at the end, each a number whose low two bits pick what to copy and the
+/// rest how many bytes.
+fn apply_bps(patch: &[u8], rom: &[u8]) -> io::Result<Vec<u8>> {
+    let (mut actions, checksums) = patch.split_at(patch.len() - 12);
+    let checksum =
+        |index: usize| u32::from_le_bytes(checksums[index * 4..index * 4 + 4].try_into().unwrap());
+
+    let source_size = varint(&mut actions)?;
+    let target_size = varint(&mut actions)?;
+    let metadata_size = varint(&mut actions)?;
+    take(&mut actions, metadata_size)?;
+
+    if rom.len() != source_size || crc32(rom) != checksum(0) {
+        return Err(invalid("patch is for a different rom"));
+    }
+
+    let mut output = Vec::new();
+    let mut source_offset = 0;
+    let mut target_offset = 0;
+    while !actions.is_empty() {
+        let action = varint(&mut actions)?;
+        let len = (action >> 2) + 1;
+        if output.len() + len > target_size {
+            return Err(invalid("patch writes past the end of the rom"));
+        }
+
+        match action & 0x3 {
+            // Source read: the same bytes as the rom, in the same place.
+            0 => {
+                let bytes = rom
+                    .get(output.len()..output.len() + len)
+                    .ok_or_else(|| invalid("patch reads past the end of the rom"))?;
+                output.extend_from_slice(bytes);
+            }
+            // Target read: bytes from the patch.
+            1 => output.extend_from_slice(take(&mut actions, len)?),
+            // Source copy: bytes from anywhere in the rom.
+            2 => {
+                source_offset = relative(source_offset, varint(&mut actions)?)?;
+                let bytes = rom
+                    .get(source_offset..)
+                    .and_then(|rest| rest.get(..len))
+                    .ok_or_else(|| invalid("patch reads past the end of the rom"))?;
+                output.extend_from_slice(bytes);
+                source_offset += len;
+            }
+            // Target copy: bytes already written, one at a time as the
+            // copy may overlap what it writes to repeat a pattern.
+            _ => {
+                target_offset = relative(target_offset, varint(&mut actions)?)?;
+                for _ in 0..len {
+                    let byte = *output
+                        .get(target_offset)
+                        .ok_or_else(|| invalid("patch copies bytes not written yet"))?;
+                    output.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+
+    if output.len() != target_size || crc32(&output) != checksum(1) {
+        return Err(invalid("patched rom doesn't match the checksum"));
+    }
+
+    Ok(output)
+}
+
+fn take<'a>(patch: &mut &'a [u8], len: usize) -> io::Result<&'a [u8]> {
+    if patch.len() < len {
+        return Err(invalid("patch is cut short"));
+    }
+    let (bytes, rest) = patch.split_at(len);
+    *patch = rest;
+    Ok(bytes)
+}
+
+fn be(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |value, &byte| value << 8 | byte as usize)
+}
+
+/// BPS numbers: 7 bits to a byte, least significant first, with the top
+/// bit set on the last byte and each continuation adding one so that every
+/// number has a single encoding.
+fn varint(patch: &mut &[u8]) -> io::Result<usize> {
+    let mut value: usize = 0;
+    let mut shift: usize = 1;
+    loop {
+        let byte = take(patch, 1)?[0];
+        value = (byte as usize & 0x7F)
+            .checked_mul(shift)
+            .and_then(|bits| value.checked_add(bits))
+            .ok_or_else(|| invalid("number in patch is too large"))?;
+        if byte & 0x80 != 0 {
+            return Ok(value);
+        }
+        shift = shift
+            .checked_mul(0x80)
+            .ok_or_else(|| invalid("number in patch is too large"))?;
+        value = value
+            .checked_add(shift)
+            .ok_or_else(|| invalid("number in patch is too large"))?;
+    }
+}
+
+/// Moves `offset` by a signed distance whose sign is in the lowest bit.
+fn relative(offset: usize, distance: usize) -> io::Result<usize> {
+    let moved = if distance & 1 == 0 {
+        offset.checked_add(distance >> 1)
+    } else {
+        offset.checked_sub(distance >> 1)
+    };
+    moved.ok_or_else(|| invalid("patch copies from outside the rom"))
+}
+
+/// CRC-32 as used by zip and PNG.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                crc >> 1 ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_varint(mut value: usize, bytes: &mut Vec<u8>) {
+        loop {
+            let low = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                bytes.push(low | 0x80);
+                return;
+            }
+            bytes.push(low);
+            value -= 1;
+        }
+    }
+
+    /// A BPS patch turning `source` into `target` with the given actions,
+    /// each an action number and its extra bytes, offsets already encoded
+    /// as single byte numbers.
+    fn bps(source: &[u8], target: &[u8], actions: &[(usize, &[u8])]) -> Vec<u8> {
+        let mut patch = BPS_MAGIC.to_vec();
+        encode_varint(source.len(), &mut patch);
+        encode_varint(target.len(), &mut patch);
+        encode_varint(0, &mut patch);
+        for (action, extra) in actions {
+            encode_varint(*action, &mut patch);
+            patch.extend_from_slice(extra);
+        }
+        patch.extend(crc32(source).to_le_bytes());
+        patch.extend(crc32(target).to_le_bytes());
+        patch.extend(crc32(&patch).to_le_bytes());
+        patch
+    }
+
+    #[test]
+    fn checksum() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn ips() {
+        let patch = [
+            b"PATCH".as_slice(),
+            // Two bytes at 0x0001.
+            &[0x00, 0x00, 0x01, 0x00, 0x02, 0xAA, 0xBB],
+            // Three 0xFF past the end.
+            &[0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x03, 0xFF],
+            b"EOF",
+        ]
+        .concat();
+
+        let patch = Patch::read_from(patch.as_slice()).unwrap();
+
+        assert_eq!(patch.format(), PatchFormat::Ips);
+        assert_eq!(
+            patch.apply(&[1, 2, 3, 4]).unwrap(),
+            [1, 0xAA, 0xBB, 4, 0, 0xFF, 0xFF, 0xFF]
+        );
+
+        let truncated = Patch::read_from(&b"PATCHEOF\x00\x00\x02"[..]).unwrap();
+        assert_eq!(truncated.apply(&[1, 2, 3, 4]).unwrap(), [1, 2]);
+
+        let cut_short = Patch::read_from(&b"PATCH\x00\x00\x01\x00\x02\xAA"[..]).unwrap();
+        assert!(cut_short.apply(&[1, 2, 3, 4]).is_err());
+    }
+
+    #[test]
+    fn bps_actions() {
+        let source = [0x12, 0x34, 0x56, 0x78];
+        let target = [0x12, 0x34, 0xAB, 0x56, 0x78, 0xCD, 0xCD, 0xCD];
+        let patch = bps(
+            &source,
+            &target,
+            &[
+                // Source read of 2 bytes.
+                ((2 - 1) << 2, &[]),
+                // Target read of 1 byte.
+                (1, &[0xAB]),
+                // Source copy of 2 bytes from 2.
+                ((2 - 1) << 2 | 2, &[0x80 | 2 << 1]),
+                // Target read of 1 byte.
+                (1, &[0xCD]),
+                // Target copy of 2 bytes from 5, repeating the byte before.
+                ((2 - 1) << 2 | 3, &[0x80 | 5 << 1]),
+            ],
+        );
+
+        let patch = Patch::read_from(patch.as_slice()).unwrap();
+
+        assert_eq!(patch.format(), PatchFormat::Bps);
+        assert_eq!(patch.apply(&source).unwrap(), target);
+    }
+
+    #[test]
+    fn bps_checksums() {
+        let source = [1, 2, 3];
+        let target = [1, 2, 3, 4];
+        let patch = bps(&source, &target, &[((3 - 1) << 2, &[]), (1, &[4])]);
+
+        let err = Patch::read_from(&patch[..patch.len() - 1]).unwrap_err();
+        assert_eq!(err.to_string(), "patch is corrupt");
+
+        let parsed = Patch::read_from(patch.as_slice()).unwrap();
+        let err = parsed.apply(&[1, 2, 4]).unwrap_err();
+        assert_eq!(err.to_string(), "patch is for a different rom");
+
+        // Claims to make something else.
+        let wrong = bps(&source, &[1, 2, 3, 5], &[((3 - 1) << 2, &[]), (1, &[4])]);
+        let err = Patch::read_from(wrong.as_slice())
+            .unwrap()
+            .apply(&source)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "patched rom doesn't match the checksum");
+
+        assert!(Patch::read_from(&b"NOTAPATCH"[..]).is_err());
+    }
+
+    #[test]
+    fn bps_number_too_large() {
+        let source = [1, 2, 3];
+        let target = [1, 2, 3, 4];
+        // A target read of the 4, then an action number that keeps going
+        // until it no longer fits in a usize.
+        let extra = [&[4][..], &[0x7F; 9]].concat();
+        let patch = bps(&source, &target, &[((3 - 1) << 2, &[]), (1, &extra)]);
+
+        let err = Patch::read_from(patch.as_slice())
+            .unwrap()
+            .apply(&source)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "number in patch is too large");
+    }
+}
@@ -0,0 +1,296 @@
+use std::fmt;
+
+use crate::analysis::walk;
+use crate::instruction::Instruction;
+use crate::quirks::Quirks;
+use crate::{Emulator, PROGRAM_START};
+
+/// Largest ROM that fits in the 4K of the original platforms.
+pub const MAX_CHIP8_ROM_SIZE: usize = Emulator::MEMORY_SIZE - PROGRAM_START as usize;
+
+/// The interpreter a ROM was written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Platform {
+    Chip8,
+    SuperChip,
+    XoChip,
+}
+
+impl Platform {
+    /// The profile name used by `Quirks::PROFILES`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Chip8 => "vip",
+            Self::SuperChip => "schip",
+            Self::XoChip => "xo-chip",
+        }
+    }
+
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Self::Chip8 => Quirks::VIP,
+            Self::SuperChip => Quirks::SCHIP,
+            Self::XoChip => Quirks::XO_CHIP,
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The opcodes CHIP-8 doesn't have, by the platform that introduced them.
+const EXTENSIONS: [(&str, Platform); 10] = [
+    ("00FF", Platform::SuperChip),
+    ("00FE", Platform::SuperChip),
+    ("00Cn", Platform::SuperChip),
+    ("Dxy0", Platform::SuperChip),
+    ("Fx30", Platform::SuperChip),
+    ("Fx75", Platform::SuperChip),
+    ("F000", Platform::XoChip),
+    ("5xy2", Platform::XoChip),
+    ("Fn01", Platform::XoChip),
+    ("F002", Platform::XoChip),
+];
+
+/// Index of `instruction` in `EXTENSIONS`.
+fn extension(instruction: Instruction) -> Option<usize> {
+    let index = match instruction {
+        Instruction(0x0, 0x0, 0xF, 0xF) => 0,
+        Instruction(0x0, 0x0, 0xF, 0xE) => 1,
+        Instruction(0x0, 0x0, 0xC, _) => 2,
+        Instruction(0xD, _, _, 0x0) => 3,
+        Instruction(0xF, _, 0x3, 0x0) => 4,
+        Instruction(0xF, _, 0x7, 0x5) => 5,
+        Instruction(0xF, 0x0, 0x0, 0x0) => 6,
+        Instruction(0x5, _, _, 0x2) => 7,
+        Instruction(0xF, _, 0x0, 0x1) => 8,
+        Instruction(0xF, 0x0, 0x0, 0x2) => 9,
+        _ => return None,
+    };
+    Some(index)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    Low,
+    Medium,
+    High,
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        })
+    }
+}
+
+/// What `guess_platform` made of a ROM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlatformGuess {
+    pub platform: Platform,
+    pub confidence: Confidence,
+    /// SUPER-CHIP only opcodes found.
+    pub schip_opcodes: u32,
+    /// XO-CHIP only opcodes found.
+    pub xo_chip_opcodes: u32,
+    /// One bit per entry of `EXTENSIONS` seen, to tell variety from
+    /// repetition.
+    seen: u16,
+}
+
+impl PlatformGuess {
+    /// The patterns of the opcodes that gave the ROM away, like `00FF`.
+    pub fn opcodes(&self) -> impl Iterator<Item = &'static str> + '_ {
+        EXTENSIONS
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| self.seen & 1 << index != 0)
+            .map(|(_, &(pattern, _))| pattern)
+    }
+
+    /// The quirks to run the ROM with, when opcodes of a later platform make
+    /// the guess at least medium confidence. A ROM without any could be
+    /// written for any interpreter, so that's left to the caller.
+    pub fn quirks(&self) -> Option<Quirks> {
+        (self.seen != 0 && self.confidence >= Confidence::Medium).then(|| self.platform.quirks())
+    }
+}
+
+impl fmt::Display for PlatformGuess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({} confidence)", self.platform, self.confidence)
+    }
+}
+
+/// Guesses the platform of a ROM no database knows from the opcodes that
+/// only later platforms have and from its size.
+///
+/// Only the code reachable from the start is decoded, so sprites that
+/// happen to look like those opcodes don't count. Code behind computed
+/// jumps isn't found either; a ROM without any such opcodes is taken for
+/// plain CHIP-8.
+pub fn guess_platform(rom: &[u8]) -> PlatformGuess {
+    let mut seen = 0u16;
+    let mut schip_opcodes = 0;
+    let mut xo_chip_opcodes = 0;
+
+    walk(rom, [PROGRAM_START], is_known, |_, instruction, _| {
+        let Some(index) = extension(instruction) else {
+            return;
+        };
+        seen |= 1 << index;
+        match EXTENSIONS[index].1 {
+            Platform::SuperChip => schip_opcodes += 1,
+            _ => xo_chip_opcodes += 1,
+        }
+    });
+
+    let patterns = |platform| {
+        EXTENSIONS
+            .iter()
+            .enumerate()
+            .filter(|&(index, &(_, of))| of == platform && seen & 1 << index != 0)
+            .count()
+    };
+
+    let (platform, confidence) = if rom.len() > MAX_CHIP8_ROM_SIZE {
+        (Platform::XoChip, Confidence::High)
+    } else if xo_chip_opcodes > 0 {
+        (
+            Platform::XoChip,
+            confidence(xo_chip_opcodes, patterns(Platform::XoChip)),
+        )
+    } else if schip_opcodes > 0 {
+        (
+            Platform::SuperChip,
+            confidence(schip_opcodes, patterns(Platform::SuperChip)),
+        )
+    } else {
+        (Platform::Chip8, Confidence::Medium)
+    };
+
+    PlatformGuess {
+        platform,
+        confidence,
+        schip_opcodes,
+        xo_chip_opcodes,
+        seen,
+    }
+}
+
+/// Any opcode of the later platforms, so the walk carries on past them. The
+/// SUPER-CHIP `EXIT` counts as unknown as nothing after it runs.
+fn is_known(instruction: &Instruction) -> bool {
+    match instruction {
+        Instruction(0x0, 0x0, 0xF, 0xD) => false,
+        _ => instruction.pattern().is_some() || extension(*instruction).is_some(),
+    }
+}
+
+fn confidence(opcodes: u32, patterns: usize) -> Confidence {
+    match (patterns, opcodes) {
+        (2.., _) => Confidence::High,
+        (_, 2..) => Confidence::Medium,
+        _ => Confidence::Low,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_chip8() {
+        let rom = [
+            0x00, 0xE0, // CLS
+            0x60, 0x05, // LD V0, 5
+            0xD0, 0x05, // DRW V0, V0, 5
+        ];
+
+        let guess = guess_platform(&rom);
+
+        assert_eq!(guess.platform, Platform::Chip8);
+        assert_eq!(guess.schip_opcodes, 0);
+        assert_eq!(guess.xo_chip_opcodes, 0);
+    }
+
+    #[test]
+    fn schip_opcodes() {
+        let lone = guess_platform(&[0x00, 0xFF]);
+        assert_eq!(lone.platform, Platform::SuperChip);
+        assert_eq!(lone.confidence, Confidence::Low);
+
+        let repeated = guess_platform(&[0xD0, 0x10, 0xD2, 0x30]);
+        assert_eq!(repeated.platform, Platform::SuperChip);
+        assert_eq!(repeated.confidence, Confidence::Medium);
+
+        let varied = guess_platform(&[0x00, 0xFF, 0x00, 0xC4, 0xF3, 0x75]);
+        assert_eq!(varied.platform, Platform::SuperChip);
+        assert_eq!(varied.confidence, Confidence::High);
+        assert_eq!(varied.schip_opcodes, 3);
+        assert!(varied.opcodes().eq(["00FF", "00Cn", "Fx75"]));
+    }
+
+    #[test]
+    fn quirks_only_when_sure() {
+        assert_eq!(guess_platform(&[0x00, 0xE0]).quirks(), None);
+        assert_eq!(guess_platform(&[0x00, 0xFF]).quirks(), None);
+        assert_eq!(
+            guess_platform(&[0xD0, 0x10, 0xD2, 0x30]).quirks(),
+            Some(Quirks::SCHIP)
+        );
+    }
+
+    #[test]
+    fn skips_data() {
+        let rom = [
+            0x12, 0x04, // 200: JP 204
+            0x00, 0xFF, // 202: sprite data
+            0xF0, 0x00, // 204: LD I, LONG 0202
+            0x02, 0x02, //
+            0x30, 0x00, // 208: SE V0, 0
+            0xF0, 0x00, // 20A: LD I, LONG 0202
+            0x02, 0x02, //
+            0x12, 0x0E, // 20E: JP 20E
+        ];
+
+        let guess = guess_platform(&rom);
+
+        assert_eq!(guess.platform, Platform::XoChip);
+        assert_eq!(guess.xo_chip_opcodes, 2, "the address isn't an opcode");
+        assert_eq!(guess.schip_opcodes, 0);
+    }
+
+    #[test]
+    fn xo_chip_wins() {
+        let rom = [
+            0x00, 0xFF, // HIGH
+            0xF2, 0x01, // PLANE 2
+            0x51, 0x22, // SAVE V1 - V2
+        ];
+
+        let guess = guess_platform(&rom);
+
+        assert_eq!(guess.platform, Platform::XoChip);
+        assert_eq!(guess.confidence, Confidence::High);
+        assert_eq!(guess.schip_opcodes, 1);
+        assert_eq!(guess.xo_chip_opcodes, 2);
+    }
+
+    #[test]
+    fn too_large_for_chip8() {
+        let rom = [0; MAX_CHIP8_ROM_SIZE + 1];
+
+        let guess = guess_platform(&rom);
+
+        assert_eq!(guess.platform, Platform::XoChip);
+        assert_eq!(guess.confidence, Confidence::High);
+        assert_eq!(guess_platform(&rom[1..]).platform, Platform::Chip8);
+    }
+}
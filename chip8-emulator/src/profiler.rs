@@ -0,0 +1,334 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+use crate::instruction::Instruction;
+use crate::observer::Observer;
+
+const HOTTEST_ADDRESSES: usize = 20;
+const STACK_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressStats {
+    pub address: u16,
+    pub count: u64,
+    /// The last instruction executed there, which only differs from the
+    /// first for self modifying code.
+    pub instruction: Instruction,
+}
+
+/// Cycles spent in a subroutine, keyed by its entry point. Inclusive counts
+/// everything until the matching `00EE`, exclusive leaves out nested calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SubroutineStats {
+    pub address: u16,
+    pub calls: u64,
+    pub inclusive: u64,
+    pub exclusive: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    entry: u16,
+    start: u64,
+    children: u64,
+}
+
+/// An `Observer` counting where the cycles of a run go: per address, per
+/// opcode and per subroutine, following `2nnn`/`00EE` pairs.
+///
+/// Every instruction counts as one cycle. Subroutines still running when
+/// the report is made, like the main loop, are counted up to now.
+#[derive(Debug, Clone, Default)]
+pub struct Profiler {
+    cycles: u64,
+    addresses: HashMap<u16, (u64, Instruction)>,
+    opcodes: BTreeMap<&'static str, u64>,
+    subroutines: HashMap<u16, SubroutineStats>,
+    stack: Vec<Frame>,
+    folded: HashMap<Vec<u16>, u64>,
+    last_flush: u64,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn total_cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Addresses by execution count, most executed first.
+    pub fn addresses(&self) -> Vec<AddressStats> {
+        let mut addresses: Vec<_> = self
+            .addresses
+            .iter()
+            .map(|(&address, &(count, instruction))| AddressStats {
+                address,
+                count,
+                instruction,
+            })
+            .collect();
+        addresses.sort_by_key(|stats| (u64::MAX - stats.count, stats.address));
+        addresses
+    }
+
+    /// Execution counts per opcode pattern such as `8xy4`, `????` being
+    /// opcodes the interpreter doesn't know.
+    pub fn opcodes(&self) -> &BTreeMap<&'static str, u64> {
+        &self.opcodes
+    }
+
+    /// Subroutines by inclusive cycles, most expensive first.
+    pub fn subroutines(&self) -> Vec<SubroutineStats> {
+        let mut subroutines = self.subroutines.clone();
+
+        // Frames that haven't returned yet count up to now, each one's
+        // inclusive time adding to its caller's children.
+        let mut children = 0;
+        for frame in self.stack.iter().rev() {
+            let inclusive = self.cycles - frame.start;
+            let stats = subroutines.entry(frame.entry).or_insert(SubroutineStats {
+                address: frame.entry,
+                ..Default::default()
+            });
+            stats.inclusive += inclusive;
+            stats.exclusive += inclusive - frame.children - children;
+            children = inclusive;
+        }
+
+        let mut subroutines: Vec<_> = subroutines.into_values().collect();
+        subroutines.sort_by_key(|stats| (u64::MAX - stats.inclusive, stats.address));
+        subroutines
+    }
+
+    pub fn write_report(&self, mut writer: impl Write) -> io::Result<()> {
+        let share = |count: u64| count as f64 * 100.0 / self.cycles.max(1) as f64;
+
+        writeln!(writer, "Total cycles: {}", self.cycles)?;
+
+        writeln!(writer)?;
+        writeln!(writer, "Hottest addresses")?;
+        writeln!(writer, "address      cycles   share  opcode")?;
+        for stats in self.addresses().iter().take(HOTTEST_ADDRESSES) {
+            writeln!(
+                writer,
+                "    {:03X} {:>11} {:>6.2}%  {:04X}",
+                stats.address,
+                stats.count,
+                share(stats.count),
+                stats.instruction.opcode()
+            )?;
+        }
+
+        writeln!(writer)?;
+        writeln!(writer, "Opcodes")?;
+        writeln!(writer, "opcode       cycles   share")?;
+        let mut opcodes: Vec<_> = self.opcodes.iter().collect();
+        opcodes.sort_by_key(|&(pattern, &count)| (u64::MAX - count, *pattern));
+        for (pattern, &count) in opcodes {
+            writeln!(writer, "  {pattern} {count:>12} {:>6.2}%", share(count))?;
+        }
+
+        writeln!(writer)?;
+        writeln!(writer, "Subroutines")?;
+        writeln!(writer, "address   calls   inclusive   exclusive")?;
+        for stats in self.subroutines() {
+            writeln!(
+                writer,
+                "    {:03X} {:>7} {:>11} {:>11}",
+                stats.address, stats.calls, stats.inclusive, stats.exclusive
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes exclusive cycles per call stack in the folded format read by
+    /// `flamegraph.pl` and inferno, e.g. `main;sub_2A4;sub_310 1234`.
+    pub fn write_folded(&self, mut writer: impl Write) -> io::Result<()> {
+        let mut folded = self.folded.clone();
+        *folded.entry(self.stack_path()).or_default() += self.cycles - self.last_flush;
+
+        let mut lines: Vec<_> = folded
+            .into_iter()
+            .filter(|&(_, cycles)| cycles > 0)
+            .map(|(path, cycles)| {
+                let mut stack = String::from("main");
+                for entry in path {
+                    stack.push_str(&format!(";sub_{entry:03X}"));
+                }
+                (stack, cycles)
+            })
+            .collect();
+        lines.sort();
+
+        for (stack, cycles) in lines {
+            writeln!(writer, "{stack} {cycles}")?;
+        }
+
+        Ok(())
+    }
+
+    fn stack_path(&self) -> Vec<u16> {
+        self.stack.iter().map(|frame| frame.entry).collect()
+    }
+
+    /// Adds the cycles since the last call or return to the current stack.
+    fn flush(&mut self) {
+        let cycles = self.cycles - self.last_flush;
+        if cycles > 0 {
+            *self.folded.entry(self.stack_path()).or_default() += cycles;
+        }
+        self.last_flush = self.cycles;
+    }
+}
+
+impl Observer for Profiler {
+    fn on_instruction(&mut self, address: u16, instruction: &Instruction) {
+        self.cycles += 1;
+
+        let entry = self.addresses.entry(address).or_insert((0, *instruction));
+        entry.0 += 1;
+        entry.1 = *instruction;
+
+        *self
+            .opcodes
+            .entry(instruction.pattern().unwrap_or("????"))
+            .or_default() += 1;
+    }
+
+    fn on_call(&mut self, _from: u16, to: u16) {
+        self.flush();
+        // Like the emulator's stack, deeper calls push out the oldest frame
+        // so programs that never return don't grow it forever.
+        if self.stack.len() == STACK_DEPTH {
+            self.stack.remove(0);
+        }
+        self.stack.push(Frame {
+            entry: to,
+            start: self.cycles,
+            children: 0,
+        });
+    }
+
+    fn on_return(&mut self, _from: u16, _to: u16) {
+        // A return without a call, the program is juggling the stack itself.
+        if self.stack.is_empty() {
+            return;
+        }
+
+        self.flush();
+        let frame = self.stack.pop().unwrap();
+        let inclusive = self.cycles - frame.start;
+
+        let stats = self
+            .subroutines
+            .entry(frame.entry)
+            .or_insert(SubroutineStats {
+                address: frame.entry,
+                ..Default::default()
+            });
+        stats.calls += 1;
+        stats.inclusive += inclusive;
+        stats.exclusive += inclusive - frame.children;
+
+        if let Some(parent) = self.stack.last_mut() {
+            parent.children += inclusive;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Emulator;
+
+    // The main loop calls 210 which calls 216 on every iteration.
+    const ROM: [u8; 26] = [
+        0x22, 0x10, // 200: CALL 210
+        0x12, 0x00, // 202: JP 200
+        0x00, 0x00, // 204
+        0x00, 0x00, // 206
+        0x00, 0x00, // 208
+        0x00, 0x00, // 20A
+        0x00, 0x00, // 20C
+        0x00, 0x00, // 20E
+        0x60, 0x01, // 210: LD V0, 1
+        0x22, 0x16, // 212: CALL 216
+        0x00, 0xEE, // 214: RET
+        0x70, 0x01, // 216: ADD V0, 1
+        0x00, 0xEE, // 218: RET
+    ];
+
+    fn profile(cycles: usize) -> Profiler {
+        let mut emulator = Emulator::new();
+        let mut profiler = Profiler::new();
+        emulator.reset();
+        emulator.load_rom(&ROM).unwrap();
+
+        for _ in 0..cycles {
+            emulator.execute_instruction_cycle_with(&mut profiler);
+        }
+        profiler
+    }
+
+    #[test]
+    fn counts() {
+        // Seven instructions per loop iteration.
+        let profiler = profile(70);
+
+        assert_eq!(profiler.total_cycles(), 70);
+        assert_eq!(profiler.addresses()[0].count, 10);
+        assert_eq!(profiler.opcodes()["00EE"], 20);
+        assert_eq!(profiler.opcodes()["2nnn"], 20);
+    }
+
+    #[test]
+    fn subroutines() {
+        let profiler = profile(70);
+        let subroutines = profiler.subroutines();
+
+        assert_eq!(
+            subroutines[0],
+            SubroutineStats {
+                address: 0x210,
+                calls: 10,
+                inclusive: 50,
+                exclusive: 30,
+            }
+        );
+        assert_eq!(
+            subroutines[1],
+            SubroutineStats {
+                address: 0x216,
+                calls: 10,
+                inclusive: 20,
+                exclusive: 20,
+            }
+        );
+    }
+
+    #[test]
+    fn open_frames_count_up_to_now() {
+        // Stops right after entering 216 for the first time.
+        let profiler = profile(4);
+        let subroutines = profiler.subroutines();
+
+        assert_eq!(subroutines[0].address, 0x210);
+        assert_eq!(subroutines[0].calls, 0);
+        assert_eq!(subroutines[0].inclusive, 3);
+        assert_eq!(subroutines[0].exclusive, 2);
+    }
+
+    #[test]
+    fn folded() {
+        let mut folded = Vec::new();
+        profile(70).write_folded(&mut folded).unwrap();
+
+        assert_eq!(
+            String::from_utf8(folded).unwrap(),
+            "main 20\nmain;sub_210 30\nmain;sub_210;sub_216 20\n"
+        );
+    }
+}
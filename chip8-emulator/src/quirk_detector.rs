@@ -0,0 +1,300 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::display::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use crate::instruction::Instruction;
+use crate::keypad::Keypad;
+use crate::observer::Observer;
+use crate::quirks::Quirks;
+use crate::{Emulator, RomTooLargeError};
+
+/// Key under which draws with lit pixels past the screen edges, the ones
+/// that wrap or get clipped, are counted next to the opcode patterns.
+pub const WRAPPING_DRAW: &str = "Dxyn wrap";
+
+/// How one profile fared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileRun {
+    pub name: &'static str,
+    pub quirks: Quirks,
+    /// How often each quirk sensitive opcode ran, by pattern like `8xy6`,
+    /// and `WRAPPING_DRAW`.
+    pub usage: BTreeMap<&'static str, u64>,
+    /// Unknown opcodes and returns without a call, signs of a program that
+    /// went off the rails.
+    pub errors: u64,
+    /// Frames that changed the picture, a rough measure of a game doing
+    /// something.
+    pub display_changes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuirkReport {
+    /// One run per profile in `Quirks::PROFILES`.
+    pub runs: Vec<ProfileRun>,
+    /// The first frame after which the screens of the profiles differ.
+    pub divergence: Option<u32>,
+    /// The profile that looks most healthy when the outputs diverge, `None`
+    /// when it doesn't matter.
+    pub recommended: Option<&'static str>,
+}
+
+impl QuirkReport {
+    /// Highest count of each quirk sensitive opcode over all runs.
+    pub fn usage(&self) -> BTreeMap<&'static str, u64> {
+        let mut usage = BTreeMap::new();
+        for run in &self.runs {
+            for (&pattern, &count) in &run.usage {
+                let max: &mut u64 = usage.entry(pattern).or_default();
+                *max = (*max).max(count);
+            }
+        }
+        usage
+    }
+
+    pub fn write_report(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "Quirk sensitive opcodes executed")?;
+        let usage = self.usage();
+        if usage.is_empty() {
+            writeln!(writer, "  none")?;
+        }
+        for (pattern, count) in usage {
+            writeln!(writer, "  {pattern:<10} {count:>10}")?;
+        }
+
+        writeln!(writer)?;
+        writeln!(writer, "profile        errors    changes")?;
+        for run in &self.runs {
+            writeln!(
+                writer,
+                "  {:<10} {:>10} {:>10}",
+                run.name, run.errors, run.display_changes
+            )?;
+        }
+
+        writeln!(writer)?;
+        match (self.divergence, self.recommended) {
+            (Some(frame), Some(profile)) => {
+                writeln!(writer, "Outputs diverge at frame {frame}.")?;
+                writeln!(writer, "Recommended profile: {profile}")?;
+            }
+            _ => writeln!(writer, "Outputs are identical, any profile works.")?,
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs a ROM under every quirk profile side by side with the same seed and
+/// input, to tell which one it was written for.
+///
+/// Only what the runs reach is compared, so a game that waits on a title
+/// screen needs input that gets it into the action.
+#[derive(Debug, Clone, Copy)]
+pub struct QuirkDetector {
+    frames: u32,
+    cycles_per_frame: u32,
+    seed: u64,
+}
+
+impl QuirkDetector {
+    pub fn new(frames: u32, cycles_per_frame: u32) -> Self {
+        Self {
+            frames,
+            cycles_per_frame,
+            seed: 0,
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// `input` is called once per frame, before its instructions, to set
+    /// the keys every run sees.
+    pub fn run(
+        &self,
+        rom: &[u8],
+        mut input: impl FnMut(u32, &mut Keypad),
+    ) -> Result<QuirkReport, RomTooLargeError> {
+        let mut runs = Vec::new();
+        for (name, quirks) in Quirks::PROFILES {
+            let mut emulator = Emulator::new();
+            emulator.set_rng_seed(self.seed);
+            emulator.set_quirks(quirks);
+            emulator.reset();
+            emulator.load_rom(rom)?;
+            runs.push((emulator, Usage::default(), name));
+        }
+
+        let mut keypad = Keypad::new();
+        let mut divergence = None;
+        for frame in 0..self.frames {
+            input(frame, &mut keypad);
+
+            for (emulator, usage, _) in &mut runs {
+                let before = *emulator.display.get_buffer();
+
+                emulator.keypad.set_state(keypad.state());
+                for _ in 0..self.cycles_per_frame {
+                    emulator.execute_instruction_cycle_with(usage);
+                }
+                emulator.decrement_timers();
+
+                if *emulator.display.get_buffer() != before {
+                    usage.display_changes += 1;
+                }
+            }
+
+            let first = runs[0].0.display.get_buffer();
+            if divergence.is_none()
+                && runs
+                    .iter()
+                    .any(|(emulator, _, _)| emulator.display.get_buffer() != first)
+            {
+                divergence = Some(frame);
+            }
+        }
+
+        let runs: Vec<_> = runs
+            .into_iter()
+            .map(|(emulator, usage, name)| ProfileRun {
+                name,
+                quirks: *emulator.quirks(),
+                usage: usage.counts,
+                errors: usage.errors,
+                display_changes: usage.display_changes,
+            })
+            .collect();
+
+        // Fewest errors first, then the liveliest screen, then the order of
+        // the profiles.
+        let recommended = divergence.and_then(|_| {
+            runs.iter()
+                .enumerate()
+                .min_by_key(|(index, run)| (run.errors, u64::MAX - run.display_changes, *index))
+                .map(|(_, run)| run.name)
+        });
+
+        Ok(QuirkReport {
+            runs,
+            divergence,
+            recommended,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    counts: BTreeMap<&'static str, u64>,
+    errors: u64,
+    depth: u64,
+    display_changes: u64,
+}
+
+impl Observer for Usage {
+    fn on_instruction(&mut self, _address: u16, instruction: &Instruction) {
+        let pattern = match instruction.pattern() {
+            Some(pattern @ ("8xy1" | "8xy2" | "8xy3" | "8xy6" | "8xyE")) => pattern,
+            Some(pattern @ ("Fx55" | "Fx65" | "Bnnn")) => pattern,
+            _ => return,
+        };
+        *self.counts.entry(pattern).or_default() += 1;
+    }
+
+    fn on_draw(&mut self, x: u8, y: u8, sprite: &[u8], _collision: bool) {
+        let x = x as usize % DISPLAY_WIDTH;
+        let y = y as usize % DISPLAY_HEIGHT;
+        // Columns past the right edge, as bits of a sprite row.
+        let past_right = 0xFFu8.checked_shr((DISPLAY_WIDTH - x) as u32).unwrap_or(0);
+        let wraps = sprite
+            .iter()
+            .enumerate()
+            .any(|(row, &bits)| (y + row >= DISPLAY_HEIGHT && bits != 0) || bits & past_right != 0);
+        if wraps {
+            *self.counts.entry(WRAPPING_DRAW).or_default() += 1;
+        }
+    }
+
+    fn on_call(&mut self, _from: u16, _to: u16) {
+        self.depth += 1;
+    }
+
+    fn on_return(&mut self, _from: u16, _to: u16) {
+        match self.depth.checked_sub(1) {
+            Some(depth) => self.depth = depth,
+            None => self.errors += 1,
+        }
+    }
+
+    fn on_unknown_opcode(&mut self, _address: u16, _instruction: &Instruction) {
+        self.errors += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_outputs() {
+        let rom = [
+            0x60, 0x05, // 200: LD V0, 5
+            0xF0, 0x29, // 202: LD F, V0
+            0xD0, 0x05, // 204: DRW V0, V0, 5
+            0x12, 0x06, // 206: JP 206
+        ];
+
+        let report = QuirkDetector::new(10, 10).run(&rom, |_, _| {}).unwrap();
+
+        assert_eq!(report.runs.len(), Quirks::PROFILES.len());
+        assert!(report.usage().is_empty());
+        assert_eq!(report.divergence, None);
+        assert_eq!(report.recommended, None);
+    }
+
+    #[test]
+    fn shift_quirk() {
+        // Draws the digit the shift leaves in V0, then jumps through a
+        // table where only the in place shift lands on valid code.
+        let rom = [
+            0x60, 0x04, // 200: LD V0, 4
+            0x61, 0x03, // 202: LD V1, 3
+            0x80, 0x16, // 204: SHR V0, V1
+            0xF0, 0x29, // 206: LD F, V0
+            0xD3, 0x35, // 208: DRW V3, V3, 5
+            0x80, 0x0E, // 20A: SHL V0, V0
+            0x82, 0x00, // 20C: LD V2, V0
+            0xB2, 0x10, // 20E: JP V0, 210
+            0x12, 0x10, // 210: JP 210
+            0xFF, 0xFF, // 212: V0 = 2 after shifting V1
+            0x12, 0x14, // 214: JP 214, V0 = 4 after shifting in place
+        ];
+
+        let report = QuirkDetector::new(5, 10).run(&rom, |_, _| {}).unwrap();
+
+        assert_eq!(report.usage()["8xy6"], 1);
+        assert_eq!(report.usage()["8xyE"], 1);
+        assert_eq!(report.usage()["Bnnn"], 1);
+        assert_eq!(report.divergence, Some(0));
+        assert_eq!(report.recommended, Some("schip"));
+    }
+
+    #[test]
+    fn counts_wrapping_draws() {
+        // The top row of the 0 glyph is 1111....; only the second draw has
+        // lit pixels past the right edge.
+        let rom = [
+            0x60, 0x3C, // 200: LD V0, 60
+            0xD0, 0x11, // 202: DRW V0, V1, 1
+            0x60, 0x3E, // 204: LD V0, 62
+            0xD0, 0x11, // 206: DRW V0, V1, 1
+            0x12, 0x08, // 208: JP 208
+        ];
+
+        let report = QuirkDetector::new(1, 10).run(&rom, |_, _| {}).unwrap();
+
+        assert_eq!(report.usage()[WRAPPING_DRAW], 1);
+    }
+}
@@ -0,0 +1,153 @@
+use core::fmt;
+use core::str::FromStr;
+
+/// Behaviour that differs between CHIP-8 interpreters, which games written
+/// for one of them rely on.
+///
+/// The default, `NONE`, is this emulator's original behaviour, which matches
+/// none of the historical interpreters exactly; the other associated
+/// constants are the usual profiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quirks {
+    /// `8xy6`/`8xyE` shift `Vy` into `Vx` instead of shifting `Vx` in place.
+    pub shift_vy: bool,
+    /// `Fx55`/`Fx65` leave `I` pointing past the last register they touched.
+    pub load_store_increments_i: bool,
+    /// `Bxnn` jumps to `xnn + Vx` instead of `nnn + V0`.
+    pub jump_vx: bool,
+    /// Sprites are cut off at the screen edges instead of wrapping around.
+    /// Where a sprite starts always wraps.
+    pub clip_sprites: bool,
+    /// `8xy1`/`8xy2`/`8xy3` set `VF` to 0.
+    pub logic_resets_vf: bool,
+}
+
+impl Quirks {
+    pub const NONE: Self = Self {
+        shift_vy: false,
+        load_store_increments_i: false,
+        jump_vx: false,
+        clip_sprites: false,
+        logic_resets_vf: false,
+    };
+
+    /// The original COSMAC VIP interpreter.
+    pub const VIP: Self = Self {
+        shift_vy: true,
+        load_store_increments_i: true,
+        jump_vx: false,
+        clip_sprites: true,
+        logic_resets_vf: true,
+    };
+
+    /// SUPER-CHIP 1.1 on the HP-48.
+    pub const SCHIP: Self = Self {
+        shift_vy: false,
+        load_store_increments_i: false,
+        jump_vx: true,
+        clip_sprites: true,
+        logic_resets_vf: false,
+    };
+
+    /// Octo's XO-CHIP.
+    pub const XO_CHIP: Self = Self {
+        shift_vy: true,
+        load_store_increments_i: true,
+        jump_vx: false,
+        clip_sprites: false,
+        logic_resets_vf: false,
+    };
+
+    /// Every named profile, in the order they're tried when guessing.
+    pub const PROFILES: [(&'static str, Self); 3] = [
+        ("vip", Self::VIP),
+        ("schip", Self::SCHIP),
+        ("xo-chip", Self::XO_CHIP),
+    ];
+
+    /// Looks up a profile by the name used in `PROFILES`.
+    pub fn profile(name: &str) -> Option<Self> {
+        Self::PROFILES
+            .iter()
+            .find(|(profile, _)| profile.eq_ignore_ascii_case(name))
+            .map(|&(_, quirks)| quirks)
+    }
+
+    fn flags(&self) -> [(&'static str, bool); 5] {
+        [
+            ("shift_vy", self.shift_vy),
+            ("load_store_increments_i", self.load_store_increments_i),
+            ("jump_vx", self.jump_vx),
+            ("clip_sprites", self.clip_sprites),
+            ("logic_resets_vf", self.logic_resets_vf),
+        ]
+    }
+
+    fn flag_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "shift_vy" => Some(&mut self.shift_vy),
+            "load_store_increments_i" => Some(&mut self.load_store_increments_i),
+            "jump_vx" => Some(&mut self.jump_vx),
+            "clip_sprites" => Some(&mut self.clip_sprites),
+            "logic_resets_vf" => Some(&mut self.logic_resets_vf),
+            _ => None,
+        }
+    }
+}
+
+/// The enabled quirks by field name, comma separated, e.g.
+/// `jump_vx,clip_sprites`, or `none`.
+impl fmt::Display for Quirks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut enabled = self.flags().into_iter().filter(|&(_, on)| on);
+        match enabled.next() {
+            Some((name, _)) => f.write_str(name)?,
+            None => return f.write_str("none"),
+        }
+        for (name, _) in enabled {
+            write!(f, ",{name}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseQuirksError;
+
+impl fmt::Display for ParseQuirksError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("quirks should be `none` or a list of quirk names")
+    }
+}
+
+impl FromStr for Quirks {
+    type Err = ParseQuirksError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut quirks = Self::NONE;
+        if s == "none" {
+            return Ok(quirks);
+        }
+
+        for name in s.split(',') {
+            *quirks.flag_mut(name.trim()).ok_or(ParseQuirksError)? = true;
+        }
+        Ok(quirks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_round_trip() {
+        for (_, quirks) in Quirks::PROFILES.into_iter().chain([("", Quirks::NONE)]) {
+            assert_eq!(quirks.to_string().parse(), Ok(quirks));
+        }
+        assert_eq!(Quirks::SCHIP.to_string(), "jump_vx,clip_sprites");
+        assert_eq!(Quirks::NONE.to_string(), "none");
+        assert_eq!("shift_vy,bogus".parse::<Quirks>(), Err(ParseQuirksError));
+    }
+}
@@ -0,0 +1,49 @@
+/// xorshift64* generator used by `Cxkk`. It's small enough for
+/// microcontrollers and, given the same seed, produces the same numbers on
+/// every platform.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub const DEFAULT_SEED: u64 = 0x2545_F491_4F6C_DD1D;
+
+    pub const fn new(seed: u64) -> Self {
+        // A zero state would only ever produce zeroes.
+        let state = if seed == 0 { Self::DEFAULT_SEED } else { seed };
+
+        Self { state }
+    }
+
+    pub fn next_u8(&mut self) -> u8 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = Rng::new(1234);
+        let mut b = Rng::new(1234);
+
+        for _ in 0..100 {
+            assert_eq!(a.next_u8(), b.next_u8());
+        }
+    }
+
+    #[test]
+    fn zero_seed() {
+        let mut rng = Rng::new(0);
+
+        assert!((0..100).any(|_| rng.next_u8() != 0));
+    }
+}
@@ -0,0 +1,75 @@
+use core::fmt;
+use core::str::FromStr;
+
+/// SHA-1 of a ROM image, the key used to match movies and settings to a
+/// game no matter what the file is called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RomHash(pub [u8; 20]);
+
+impl RomHash {
+    pub fn of(rom: &[u8]) -> Self {
+        Self(sha1_smol::Sha1::from(rom).digest().bytes())
+    }
+}
+
+impl fmt::Display for RomHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseRomHashError;
+
+impl fmt::Display for ParseRomHashError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("rom hash should be 40 hex digits")
+    }
+}
+
+impl FromStr for RomHash {
+    type Err = ParseRomHashError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 40 || !s.is_ascii() {
+            return Err(ParseRomHashError);
+        }
+
+        let mut hash = [0; 20];
+        for (i, byte) in hash.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).map_err(|_| ParseRomHashError)?;
+        }
+
+        Ok(Self(hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_hash() {
+        assert_eq!(
+            RomHash::of(b"abc").to_string(),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+    }
+
+    #[test]
+    fn parse() {
+        let hash = RomHash::of(&[0x12, 0x00]);
+
+        assert_eq!(hash.to_string().parse(), Ok(hash));
+        assert_eq!(
+            hash.to_string().to_uppercase().parse(),
+            Ok(hash),
+            "hex digits are case insensitive"
+        );
+        assert_eq!("abc".parse::<RomHash>(), Err(ParseRomHashError));
+    }
+}
@@ -0,0 +1,58 @@
+use std::io::{self, Write};
+
+use crate::display::{indexed_pixels, Display, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use crate::palette::Palette;
+
+impl Display {
+    /// Encodes the framebuffer as a PNG with every pixel blown up to a
+    /// `scale` x `scale` square.
+    pub fn write_png(&self, writer: impl Write, scale: u32, palette: Palette) -> io::Result<()> {
+        let scale = scale.max(1) as usize;
+        let width = DISPLAY_WIDTH * scale;
+        let height = DISPLAY_HEIGHT * scale;
+
+        let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette([palette.off, palette.on].concat());
+
+        encoder
+            .write_header()
+            .and_then(|mut writer| {
+                writer.write_image_data(&indexed_pixels(self.get_buffer(), scale))
+            })
+            .map_err(|err| match err {
+                png::EncodingError::IoError(err) => err,
+                err => io::Error::other(err),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut display = Display::new();
+        display.draw(0, 0, &[0x80]);
+        display.draw(63, 31, &[0x80]);
+
+        let palette = Palette::new([0x10, 0x20, 0x30], [0xF0, 0xE0, 0xD0]);
+        let mut file = Vec::new();
+        display.write_png(&mut file, 2, palette).unwrap();
+
+        let mut decoder = png::Decoder::new(&file[..]);
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+
+        assert_eq!((info.width, info.height), (128, 64));
+        let pixel = |x: usize, y: usize| &pixels[(x + y * 128) * 3..][..3];
+        assert_eq!(pixel(0, 0), palette.on);
+        assert_eq!(pixel(1, 1), palette.on, "pixels are scaled");
+        assert_eq!(pixel(2, 0), palette.off);
+        assert_eq!(pixel(127, 63), palette.on);
+    }
+}
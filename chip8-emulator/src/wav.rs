@@ -0,0 +1,132 @@
+use std::io::{self, Seek, SeekFrom, Write};
+
+use crate::audio::{AudioSynth, FrameSound};
+
+const HEADER_LEN: u32 = 44;
+
+/// Writes mono 16 bit PCM samples to a WAV file. The chunk sizes in the
+/// header are only filled in by `finish`, so a writer that is dropped early
+/// leaves a file most players treat as empty.
+#[derive(Debug)]
+pub struct WavWriter<W: Write + Seek> {
+    writer: W,
+    data_len: u32,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(mut writer: W, sample_rate: u32) -> io::Result<Self> {
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+
+        writer.write_all(b"fmt ")?;
+        writer.write_all(&16u32.to_le_bytes())?;
+        writer.write_all(&1u16.to_le_bytes())?; // PCM
+        writer.write_all(&1u16.to_le_bytes())?; // mono
+        writer.write_all(&sample_rate.to_le_bytes())?;
+        writer.write_all(&(sample_rate * 2).to_le_bytes())?; // bytes per second
+        writer.write_all(&2u16.to_le_bytes())?; // bytes per sample
+        writer.write_all(&16u16.to_le_bytes())?; // bits per sample
+
+        writer.write_all(b"data")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+
+        Ok(Self {
+            writer,
+            data_len: 0,
+        })
+    }
+
+    pub fn write_samples(&mut self, samples: &[f32]) -> io::Result<()> {
+        for &sample in samples {
+            let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.writer.write_all(&sample.to_le_bytes())?;
+        }
+        self.data_len += samples.len() as u32 * 2;
+
+        Ok(())
+    }
+
+    /// Patches the header with the final sizes and hands back the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.seek(SeekFrom::Start(4))?;
+        self.writer
+            .write_all(&(HEADER_LEN - 8 + self.data_len).to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(HEADER_LEN as u64 - 4))?;
+        self.writer.write_all(&self.data_len.to_le_bytes())?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Turns the buzzer activity of every frame into a WAV file. Call
+/// `record_frame` with `Emulator::last_frame_sound` after each
+/// `decrement_timers`.
+#[derive(Debug)]
+pub struct WavRecorder<W: Write + Seek> {
+    synth: AudioSynth,
+    wav: WavWriter<W>,
+    samples: Vec<f32>,
+}
+
+impl<W: Write + Seek> WavRecorder<W> {
+    pub const SAMPLE_RATE: u32 = 44100;
+
+    pub fn new(writer: W, cycles_per_frame: u32) -> io::Result<Self> {
+        Ok(Self {
+            synth: AudioSynth::new(Self::SAMPLE_RATE, cycles_per_frame),
+            wav: WavWriter::new(writer, Self::SAMPLE_RATE)?,
+            samples: Vec::new(),
+        })
+    }
+
+    pub fn record_frame(&mut self, sound: &FrameSound) -> io::Result<()> {
+        self.samples.resize(self.synth.next_frame_len(), 0.0);
+        self.synth.render_frame(sound, &mut self.samples);
+
+        self.wav.write_samples(&self.samples)
+    }
+
+    pub fn finish(self) -> io::Result<W> {
+        self.wav.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn header() {
+        let mut wav = WavWriter::new(Cursor::new(Vec::new()), 8000).unwrap();
+        wav.write_samples(&[0.0, 1.0, -1.0]).unwrap();
+        let file = wav.finish().unwrap().into_inner();
+
+        assert_eq!(file.len(), 44 + 6);
+        assert_eq!(&file[0..4], b"RIFF");
+        assert_eq!(&file[4..8], &42u32.to_le_bytes());
+        assert_eq!(&file[24..28], &8000u32.to_le_bytes());
+        assert_eq!(&file[40..44], &6u32.to_le_bytes());
+        assert_eq!(&file[44..], &[0x00, 0x00, 0xFF, 0x7F, 0x01, 0x80]);
+    }
+
+    #[test]
+    fn one_second_of_frames() {
+        let mut recorder = WavRecorder::new(Cursor::new(Vec::new()), 10).unwrap();
+        for frame in 0..60 {
+            recorder.record_frame(&FrameSound::new(frame < 30)).unwrap();
+        }
+        let file = recorder.finish().unwrap().into_inner();
+
+        let samples: Vec<i16> = file[44..]
+            .chunks(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+            .collect();
+        assert_eq!(samples.len(), 44100);
+        assert!(samples[..22050].iter().any(|&sample| sample != 0));
+        assert!(samples[22050..].iter().all(|&sample| sample == 0));
+    }
+}
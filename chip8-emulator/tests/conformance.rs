@@ -0,0 +1,154 @@
+//! End-to-end checks: every case runs a ROM from `tests/roms` for a number
+//! of frames with scripted input and compares the final framebuffer with
+//! `tests/golden/<name>.txt`, a SHA-1 of the pixels followed by an ASCII
+//! dump of them.
+//!
+//! The ROMs are hand assembled, `tests/roms/<name>.asm` has the listing.
+//! After an intended change in behaviour, rerun with `UPDATE_GOLDEN=1` to
+//! rewrite the golden files and review the diff.
+
+use chip8_emulator::{Emulator, Quirks};
+use std::fs;
+use std::path::PathBuf;
+
+const CYCLES_PER_FRAME: u32 = 10;
+
+struct Case {
+    name: &'static str,
+    rom: &'static str,
+    quirks: Quirks,
+    frames: u32,
+    /// `(frame, key, pressed)`, applied before the frame's instructions.
+    input: &'static [(u32, u8, bool)],
+}
+
+const CASES: &[Case] = &[
+    Case {
+        name: "flags",
+        rom: "flags",
+        quirks: Quirks::NONE,
+        frames: 30,
+        input: &[],
+    },
+    Case {
+        name: "bcd",
+        rom: "bcd",
+        quirks: Quirks::NONE,
+        frames: 30,
+        input: &[],
+    },
+    Case {
+        name: "keys",
+        rom: "keys",
+        quirks: Quirks::NONE,
+        frames: 60,
+        input: &[
+            (10, 0x7, true),
+            (12, 0x7, false),
+            (30, 0xA, true),
+            (40, 0xA, false),
+        ],
+    },
+    Case {
+        name: "draw",
+        rom: "draw",
+        quirks: Quirks::NONE,
+        frames: 30,
+        input: &[],
+    },
+    Case {
+        name: "quirks",
+        rom: "quirks",
+        quirks: Quirks::NONE,
+        frames: 30,
+        input: &[],
+    },
+    Case {
+        name: "quirks-vip",
+        rom: "quirks",
+        quirks: Quirks::VIP,
+        frames: 30,
+        input: &[],
+    },
+    Case {
+        name: "quirks-schip",
+        rom: "quirks",
+        quirks: Quirks::SCHIP,
+        frames: 30,
+        input: &[],
+    },
+];
+
+fn path(directory: &str, file: &str) -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "tests", directory, file]
+        .iter()
+        .collect()
+}
+
+fn run(case: &Case) -> Emulator {
+    let rom = fs::read(path("roms", &format!("{}.ch8", case.rom))).expect("read rom");
+
+    let mut emulator = Emulator::new();
+    emulator.reset();
+    emulator.set_rng_seed(1);
+    emulator.set_quirks(case.quirks);
+    emulator.load_rom(&rom).unwrap();
+
+    for frame in 0..case.frames {
+        for &(_, key, pressed) in case.input.iter().filter(|input| input.0 == frame) {
+            if pressed {
+                emulator.keypad.key_down(key);
+            } else {
+                emulator.keypad.key_up(key);
+            }
+        }
+
+        for _ in 0..CYCLES_PER_FRAME {
+            emulator.execute_instruction_cycle();
+        }
+        emulator.decrement_timers();
+    }
+
+    emulator
+}
+
+fn golden(emulator: &Emulator) -> String {
+    let pixels: Vec<u8> = emulator
+        .display
+        .get_buffer()
+        .iter()
+        .map(|&pixel| pixel as u8)
+        .collect();
+
+    format!(
+        "sha1 {}\n{}",
+        sha1_smol::Sha1::from(pixels).digest(),
+        emulator.display
+    )
+}
+
+#[test]
+fn golden_framebuffers() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut failures = Vec::new();
+
+    for case in CASES {
+        let actual = golden(&run(case));
+        let golden_path = path("golden", &format!("{}.txt", case.name));
+
+        if update {
+            fs::write(&golden_path, &actual).expect("write golden file");
+            continue;
+        }
+
+        let expected = fs::read_to_string(&golden_path).unwrap_or_default();
+        if actual.lines().next() != expected.lines().next() {
+            failures.push(format!(
+                "{}: framebuffer differs\n--- expected\n{expected}--- actual\n{actual}",
+                case.name
+            ));
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
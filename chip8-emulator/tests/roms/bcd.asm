@@ -0,0 +1,66 @@
+; bcd.ch8 - Fx33 binary coded decimal
+;
+  200: 6C 00  LD VC, 0
+  202: 6D 00  LD VD, 0
+  204: 60 00  LD V0, 0            ; 0
+  206: A2 76  LD I, buffer
+  208: F0 33  LD B, V0
+  20A: F2 65  LD V2, [I]
+  20C: 83 20  LD V3, V2
+  20E: 82 00  LD V2, V0           ; hundreds
+  210: 22 66  CALL digit
+  212: 82 10  LD V2, V1           ; tens
+  214: 22 66  CALL digit
+  216: 82 30  LD V2, V3           ; ones
+  218: 22 66  CALL digit
+  21A: 22 6E  CALL newline
+  21C: 60 09  LD V0, 9            ; 9
+  21E: A2 76  LD I, buffer
+  220: F0 33  LD B, V0
+  222: F2 65  LD V2, [I]
+  224: 83 20  LD V3, V2
+  226: 82 00  LD V2, V0           ; hundreds
+  228: 22 66  CALL digit
+  22A: 82 10  LD V2, V1           ; tens
+  22C: 22 66  CALL digit
+  22E: 82 30  LD V2, V3           ; ones
+  230: 22 66  CALL digit
+  232: 22 6E  CALL newline
+  234: 60 80  LD V0, 128          ; 128
+  236: A2 76  LD I, buffer
+  238: F0 33  LD B, V0
+  23A: F2 65  LD V2, [I]
+  23C: 83 20  LD V3, V2
+  23E: 82 00  LD V2, V0           ; hundreds
+  240: 22 66  CALL digit
+  242: 82 10  LD V2, V1           ; tens
+  244: 22 66  CALL digit
+  246: 82 30  LD V2, V3           ; ones
+  248: 22 66  CALL digit
+  24A: 22 6E  CALL newline
+  24C: 60 FF  LD V0, 255          ; 255
+  24E: A2 76  LD I, buffer
+  250: F0 33  LD B, V0
+  252: F2 65  LD V2, [I]
+  254: 83 20  LD V3, V2
+  256: 82 00  LD V2, V0           ; hundreds
+  258: 22 66  CALL digit
+  25A: 82 10  LD V2, V1           ; tens
+  25C: 22 66  CALL digit
+  25E: 82 30  LD V2, V3           ; ones
+  260: 22 66  CALL digit
+  262: 22 6E  CALL newline
+  264: 12 74  JP halt
+digit:
+  266: F2 29  LD F, V2            ; glyph for V2
+  268: DC D5  DRW VC, VD, 5
+  26A: 7C 05  ADD VC, 5           ; advance the cursor
+  26C: 00 EE  RET
+newline:
+  26E: 6C 00  LD VC, 0
+  270: 7D 06  ADD VD, 6           ; next row
+  272: 00 EE  RET
+halt:
+  274: 12 74  JP halt             ; done, spin here
+buffer:
+  276: 00 00 00                ; BCD digits
@@ -0,0 +1,42 @@
+; draw.ch8 - Dxyn collision and wrapping
+;
+  200: 6C 00  LD VC, 0
+  202: 6D 00  LD VD, 0
+  204: A2 48  LD I, block
+  206: 60 00  LD V0, 0
+  208: 61 00  LD V1, 0
+  20A: D0 14  DRW V0, V1, 4       ; block at the top left
+  20C: 82 F0  LD V2, VF           ; no collision: 0
+  20E: 6C 0A  LD VC, 10
+  210: 22 3E  CALL digit
+  212: A2 48  LD I, block         ; digit moved I to the font
+  214: 60 02  LD V0, 2
+  216: 61 02  LD V1, 2
+  218: D0 14  DRW V0, V1, 4       ; overlapping block
+  21A: 82 F0  LD V2, VF           ; collision: 1
+  21C: 6C 14  LD VC, 20
+  21E: 22 3E  CALL digit
+  220: A2 48  LD I, block
+  222: 60 3C  LD V0, 60
+  224: 61 1E  LD V1, 30
+  226: D0 14  DRW V0, V1, 4       ; bottom right corner, wraps
+  228: 60 46  LD V0, 70
+  22A: 61 0C  LD V1, 12
+  22C: D0 14  DRW V0, V1, 4       ; x past the edge wraps to 6
+  22E: 60 1E  LD V0, 30
+  230: 61 0C  LD V1, 12
+  232: D0 14  DRW V0, V1, 4
+  234: D0 14  DRW V0, V1, 4       ; draw twice to erase
+  236: 82 F0  LD V2, VF           ; collision: 1
+  238: 6C 1E  LD VC, 30
+  23A: 22 3E  CALL digit
+  23C: 12 46  JP halt
+digit:
+  23E: F2 29  LD F, V2            ; glyph for V2
+  240: DC D5  DRW VC, VD, 5
+  242: 7C 05  ADD VC, 5           ; advance the cursor
+  244: 00 EE  RET
+halt:
+  246: 12 46  JP halt             ; done, spin here
+block:
+  248: F0 90 90 F0             ; 4x4 outline
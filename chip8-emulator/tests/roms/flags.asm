@@ -0,0 +1,74 @@
+; flags.ch8 - VF after arithmetic and shifts
+;
+  200: 6C 00  LD VC, 0
+  202: 6D 00  LD VD, 0
+  204: 60 FF  LD V0, 0xFF
+  206: 61 01  LD V1, 0x01
+  208: 80 14  ADD V0, V1          ; FF + 01 carries: 1
+  20A: 82 F0  LD V2, VF           ; show the flag
+  20C: 22 7A  CALL digit
+  20E: 60 01  LD V0, 0x01
+  210: 61 01  LD V1, 0x01
+  212: 80 14  ADD V0, V1          ; 01 + 01: 0
+  214: 82 F0  LD V2, VF           ; show the flag
+  216: 22 7A  CALL digit
+  218: 60 01  LD V0, 0x01
+  21A: 61 02  LD V1, 0x02
+  21C: 80 15  SUB V0, V1          ; 01 - 02 borrows: 0
+  21E: 82 F0  LD V2, VF           ; show the flag
+  220: 22 7A  CALL digit
+  222: 60 02  LD V0, 0x02
+  224: 61 01  LD V1, 0x01
+  226: 80 15  SUB V0, V1          ; 02 - 01: 1
+  228: 82 F0  LD V2, VF           ; show the flag
+  22A: 22 7A  CALL digit
+  22C: 60 02  LD V0, 0x02
+  22E: 61 01  LD V1, 0x01
+  230: 80 17  SUBN V0, V1         ; 01 - 02 borrows: 0
+  232: 82 F0  LD V2, VF           ; show the flag
+  234: 22 7A  CALL digit
+  236: 60 01  LD V0, 0x01
+  238: 61 02  LD V1, 0x02
+  23A: 80 17  SUBN V0, V1         ; 02 - 01: 1
+  23C: 82 F0  LD V2, VF           ; show the flag
+  23E: 22 7A  CALL digit
+  240: 60 03  LD V0, 0x03
+  242: 80 06  SHR V0, V0          ; shifts out a 1
+  244: 82 F0  LD V2, VF           ; show the flag
+  246: 22 7A  CALL digit
+  248: 60 80  LD V0, 0x80
+  24A: 80 0E  SHL V0, V0          ; shifts out a 1
+  24C: 82 F0  LD V2, VF           ; show the flag
+  24E: 22 7A  CALL digit
+  250: 22 82  CALL newline
+  252: 6F FF  LD VF, 0xFF
+  254: 61 01  LD V1, 0x01
+  256: 8F 14  ADD VF, V1          ; the flag wins over the result: 1
+  258: 82 F0  LD V2, VF
+  25A: 22 7A  CALL digit
+  25C: 6F 05  LD VF, 0x05
+  25E: 61 03  LD V1, 0x03
+  260: 8F 15  SUB VF, V1          ; 05 - 03 without borrow: 1
+  262: 82 F0  LD V2, VF
+  264: 22 7A  CALL digit
+  266: 60 10  LD V0, 0x10
+  268: 61 20  LD V1, 0x20
+  26A: 80 14  ADD V0, V1          ; result 30, high nibble 3
+  26C: 82 00  LD V2, V0
+  26E: 82 26  SHR V2, V2
+  270: 82 26  SHR V2, V2
+  272: 82 26  SHR V2, V2
+  274: 82 26  SHR V2, V2
+  276: 22 7A  CALL digit
+  278: 12 88  JP halt
+digit:
+  27A: F2 29  LD F, V2            ; glyph for V2
+  27C: DC D5  DRW VC, VD, 5
+  27E: 7C 05  ADD VC, 5           ; advance the cursor
+  280: 00 EE  RET
+newline:
+  282: 6C 00  LD VC, 0
+  284: 7D 06  ADD VD, 6           ; next row
+  286: 00 EE  RET
+halt:
+  288: 12 88  JP halt             ; done, spin here
@@ -0,0 +1,25 @@
+; keys.ch8 - Fx0A, Ex9E and ExA1 with scripted input
+;
+  200: 6C 00  LD VC, 0
+  202: 6D 00  LD VD, 0
+  204: F2 0A  LD V2, K            ; wait for a key
+  206: 22 1C  CALL digit          ; show it
+  208: 61 0A  LD V1, 0xA
+wait_a:
+  20A: E1 9E  SKP V1              ; until A is held
+  20C: 12 0A  JP wait_a
+  20E: 82 10  LD V2, V1
+  210: 22 1C  CALL digit
+wait_release:
+  212: E1 A1  SKNP V1             ; until A is released
+  214: 12 12  JP wait_release
+  216: 62 00  LD V2, 0
+  218: 22 1C  CALL digit
+  21A: 12 24  JP halt
+digit:
+  21C: F2 29  LD F, V2            ; glyph for V2
+  21E: DC D5  DRW VC, VD, 5
+  220: 7C 05  ADD VC, 5           ; advance the cursor
+  222: 00 EE  RET
+halt:
+  224: 12 24  JP halt             ; done, spin here
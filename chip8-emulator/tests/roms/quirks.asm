@@ -0,0 +1,40 @@
+; quirks.ch8 - behaviour that differs between interpreters
+;
+  200: 6C 00  LD VC, 0
+  202: 6D 00  LD VD, 0
+  204: 60 03  LD V0, 3
+  206: 61 04  LD V1, 4
+  208: 80 16  SHR V0, V1          ; shifts V0 (1) rather than V1 (2)
+  20A: 82 00  LD V2, V0
+  20C: 22 36  CALL digit
+  20E: 60 01  LD V0, 1
+  210: 61 02  LD V1, 2
+  212: A2 40  LD I, buffer
+  214: F1 55  LD [I], V1          ; store V0-V1
+  216: F0 65  LD V0, [I]          ; I unchanged reads 1, incremented reads 0
+  218: 82 00  LD V2, V0
+  21A: 22 36  CALL digit
+  21C: 6F 05  LD VF, 5
+  21E: 60 00  LD V0, 0
+  220: 80 01  OR V0, V0           ; VF kept (5) or reset (0)
+  222: 82 F0  LD V2, VF
+  224: 22 36  CALL digit
+  226: 60 00  LD V0, 0
+  228: 62 04  LD V2, 4
+  22A: B2 2C  JP V0, jump_table   ; lands on +0 with V0, +4 with V2
+jump_table:
+  22C: 62 0A  LD V2, 0xA          ; jumped with V0
+  22E: 12 32  JP jumped
+  230: 62 0B  LD V2, 0xB          ; jumped with V2
+jumped:
+  232: 22 36  CALL digit
+  234: 12 3E  JP halt
+digit:
+  236: F2 29  LD F, V2            ; glyph for V2
+  238: DC D5  DRW VC, VD, 5
+  23A: 7C 05  ADD VC, 5           ; advance the cursor
+  23C: 00 EE  RET
+halt:
+  23E: 12 3E  JP halt             ; done, spin here
+buffer:
+  240: 00 00 00                
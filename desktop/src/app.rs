@@ -1,39 +1,323 @@
 use crate::audio::AudioDevice;
-use crate::input::map_keycode;
+use crate::input::{map_game_keycode, map_keycode};
 use crate::window::WindowState;
-use chip8_emulator::Emulator;
-use std::time::{Duration, Instant};
+use chip8_emulator::{
+    guess_platform, AudioSynth, Cheats, Emulator, GifRecorder, KeyMapping, Movie, MoviePlayer,
+    MovieRecorder, Observer, Palette, Quirks, RomDatabase, RomHash, RomInfo, WavRecorder,
+};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use winit::application::ApplicationHandler;
 use winit::event::{ElementState, KeyEvent, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, ControlFlow};
 use winit::keyboard::{KeyCode, PhysicalKey};
 use winit::window::WindowId;
 
+/// Collects what changed during a frame so the window is only redrawn, and
+/// the RPL flags only saved, when the emulator reports it.
+#[derive(Default)]
+struct FrameEvents {
+    redraw: bool,
+    saved_flags: Option<[u8; 16]>,
+}
+
+impl Observer for FrameEvents {
+    fn on_clear_screen(&mut self) {
+        self.redraw = true;
+    }
+
+    fn on_draw(&mut self, _x: u8, _y: u8, _sprite: &[u8], _collision: bool) {
+        self.redraw = true;
+    }
+
+    fn on_save_flags(&mut self, flags: &[u8; 16]) {
+        self.saved_flags = Some(*flags);
+    }
+}
+
+const CYCLES_PER_FRAME: u32 = 10;
+const SCREENSHOT_SCALE: u32 = 10;
+const WINDOW_TITLE: &str = "Chip-8 Emulator";
+/// Where the RPL flags of every ROM are kept, as `<rom hash>.flags`.
+const FLAGS_DIR: &str = "flags";
+
+/// A file name in the working directory that won't clash with earlier
+/// captures, e.g. `chip8-1700000000123.png`.
+fn timestamped_path(extension: &str) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_millis());
+
+    PathBuf::from(format!("chip8-{timestamp}.{extension}"))
+}
+
+pub struct Options {
+    /// Settings that came with the ROM, like those of an Octo cartridge.
+    /// Looked up in the ROM database when `None`.
+    pub info: Option<RomInfo>,
+    /// Where to save the keypad input of this session on exit.
+    pub record: Option<PathBuf>,
+    /// A movie to replay instead of reading the keyboard.
+    pub movie: Option<Movie>,
+    /// Where to record the audio of the whole session.
+    pub wav: Option<PathBuf>,
+    /// Values held before every frame, toggled with F6.
+    pub cheats: Cheats,
+}
+
 pub struct App {
     emulator: Emulator,
     audio: AudioDevice,
+    synth: AudioSynth,
+    samples: Vec<f32>,
     state: Option<WindowState>,
+    title: String,
+    palette: Palette,
+    keys: KeyMapping,
     last_tick: Instant,
+    cycles_per_frame: u32,
+    recorder: Option<(MovieRecorder, PathBuf)>,
+    player: Option<MoviePlayer>,
+    wav: Option<(WavRecorder<BufWriter<File>>, PathBuf)>,
+    gif: Option<(GifRecorder<BufWriter<File>>, PathBuf)>,
+    flags_path: PathBuf,
+    cheats: Cheats,
+    cheats_enabled: bool,
+    /// Recording or playing a movie, which cheats or RPL flags left by
+    /// other sessions would throw off.
+    movie_mode: bool,
 }
 
 impl App {
-    pub fn new(rom: &[u8]) -> Self {
+    pub fn new(rom: &[u8], options: Options) -> Self {
+        let info = options
+            .info
+            .or_else(|| RomDatabase::builtin().lookup(&RomHash::of(rom)));
+        if let Some(info) = &info {
+            println!("Loaded {}", info.title);
+        }
+
+        let movie_mode = options.record.is_some() || options.movie.is_some();
+        let (seed, cycles_per_frame, quirks) = match &options.movie {
+            Some(movie) => (movie.seed, movie.cycles_per_frame, movie.quirks),
+            None => (
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |time| time.as_nanos() as u64),
+                info.as_ref()
+                    .and_then(|info| info.tickrate)
+                    .unwrap_or(CYCLES_PER_FRAME),
+                info.as_ref()
+                    .and_then(|info| info.quirks)
+                    .unwrap_or_else(|| {
+                        let guess = guess_platform(rom);
+                        match guess.quirks() {
+                            Some(quirks) => {
+                                println!("Unknown ROM, running it as {guess}");
+                                quirks
+                            }
+                            None => {
+                                println!("Unknown ROM, running it without quirks");
+                                Quirks::NONE
+                            }
+                        }
+                    }),
+            ),
+        };
+
         let mut emulator = Emulator::new();
+        emulator.set_rng_seed(seed);
+        emulator.set_quirks(quirks);
         emulator.reset();
-        emulator.load_rom(rom);
+        if let Err(err) = emulator.load_rom(rom) {
+            eprintln!("Could not load rom: {err}");
+            std::process::exit(1);
+        }
+
+        let flags_path = Path::new(FLAGS_DIR).join(format!("{}.flags", RomHash::of(rom)));
+        // Movies always start from blank flags.
+        if !movie_mode {
+            if let Ok(flags) = fs::read(&flags_path) {
+                match flags.try_into() {
+                    Ok(flags) => emulator.set_rpl_flags(flags),
+                    Err(_) => eprintln!("Ignoring invalid flags {}", flags_path.display()),
+                }
+            }
+        }
+
+        let audio = AudioDevice::new();
+        let synth = AudioSynth::new(audio.sample_rate(), cycles_per_frame);
 
-        Self {
+        let mut app = Self {
             emulator,
-            audio: AudioDevice::new(),
+            audio,
+            synth,
+            samples: Vec::new(),
             state: None,
+            title: info.as_ref().map_or(WINDOW_TITLE.to_owned(), |info| {
+                format!("{} - {WINDOW_TITLE}", info.title)
+            }),
+            palette: info
+                .as_ref()
+                .and_then(|info| info.palette)
+                .unwrap_or_default(),
+            keys: info.map(|info| info.keys).unwrap_or_default(),
             last_tick: Instant::now(),
+            cycles_per_frame,
+            recorder: options.record.map(|path| {
+                (
+                    MovieRecorder::new(rom, seed, cycles_per_frame, quirks),
+                    path,
+                )
+            }),
+            player: options.movie.map(MoviePlayer::new),
+            wav: None,
+            gif: None,
+            flags_path,
+            cheats: options.cheats,
+            cheats_enabled: !movie_mode,
+            movie_mode,
+        };
+        for cheat in app.cheats.iter() {
+            println!("Cheat: {}", cheat.name);
+        }
+        if movie_mode && !app.cheats.is_empty() {
+            println!("Cheats are off while recording or playing a movie");
+        }
+        if let Some(path) = options.wav {
+            app.start_wav(path);
+        }
+
+        app
+    }
+
+    fn save_movie(&mut self) {
+        let Some((recorder, path)) = self.recorder.take() else {
+            return;
+        };
+
+        let result =
+            File::create(&path).and_then(|file| recorder.finish().write_to(BufWriter::new(file)));
+        match result {
+            Ok(()) => println!("Saved movie to {}", path.display()),
+            Err(err) => eprintln!("Could not save movie {}: {err}", path.display()),
+        }
+    }
+
+    fn start_wav(&mut self, path: PathBuf) {
+        let result = File::create(&path)
+            .and_then(|file| WavRecorder::new(BufWriter::new(file), self.cycles_per_frame));
+        match result {
+            Ok(recorder) => {
+                println!("Recording audio to {}", path.display());
+                self.wav = Some((recorder, path));
+            }
+            Err(err) => eprintln!("Could not create {}: {err}", path.display()),
+        }
+    }
+
+    fn stop_wav(&mut self) {
+        let Some((recorder, path)) = self.wav.take() else {
+            return;
+        };
+
+        match recorder.finish() {
+            Ok(_) => println!("Saved audio to {}", path.display()),
+            Err(err) => eprintln!("Could not save audio {}: {err}", path.display()),
+        }
+    }
+
+    fn toggle_wav(&mut self) {
+        if self.wav.is_some() {
+            self.stop_wav();
+        } else {
+            self.start_wav(timestamped_path("wav"));
         }
     }
+
+    fn toggle_cheats(&mut self) {
+        if self.movie_mode {
+            println!("Cheats are off while recording or playing a movie");
+            return;
+        }
+        self.cheats_enabled = !self.cheats_enabled;
+        println!("Cheats {}", if self.cheats_enabled { "on" } else { "off" });
+    }
+
+    fn save_flags(&self, flags: &[u8; 16]) {
+        let result =
+            fs::create_dir_all(FLAGS_DIR).and_then(|()| fs::write(&self.flags_path, flags));
+        if let Err(err) = result {
+            eprintln!("Could not save flags {}: {err}", self.flags_path.display());
+        }
+    }
+
+    fn save_screenshot(&self) {
+        let path = timestamped_path("png");
+        let result = File::create(&path).and_then(|file| {
+            self.emulator
+                .display
+                .write_png(BufWriter::new(file), SCREENSHOT_SCALE, self.palette)
+        });
+        match result {
+            Ok(()) => println!("Saved screenshot to {}", path.display()),
+            Err(err) => eprintln!("Could not save screenshot {}: {err}", path.display()),
+        }
+    }
+
+    fn start_gif(&mut self, path: PathBuf) {
+        let result = File::create(&path).and_then(|file| {
+            GifRecorder::new(BufWriter::new(file), SCREENSHOT_SCALE, self.palette)
+        });
+        match result {
+            Ok(recorder) => {
+                println!("Recording animation to {}", path.display());
+                self.gif = Some((recorder, path));
+            }
+            Err(err) => eprintln!("Could not create {}: {err}", path.display()),
+        }
+    }
+
+    fn stop_gif(&mut self) {
+        let Some((recorder, path)) = self.gif.take() else {
+            return;
+        };
+
+        match recorder.finish() {
+            Ok(_) => println!("Saved animation to {}", path.display()),
+            Err(err) => eprintln!("Could not save animation {}: {err}", path.display()),
+        }
+    }
+
+    fn toggle_gif(&mut self) {
+        if self.gif.is_some() {
+            self.stop_gif();
+        } else {
+            self.start_gif(timestamped_path("gif"));
+        }
+    }
+
+    /// Runs the capture and cheat hotkeys, returning whether `key` is one of them so
+    /// it isn't passed on to the keypad.
+    fn handle_hotkey(&mut self, key: KeyCode, pressed: bool) -> bool {
+        match key {
+            KeyCode::F6 if pressed => self.toggle_cheats(),
+            KeyCode::F8 if pressed => self.toggle_wav(),
+            KeyCode::F10 if pressed => self.toggle_gif(),
+            KeyCode::F12 if pressed => self.save_screenshot(),
+            KeyCode::F6 | KeyCode::F8 | KeyCode::F10 | KeyCode::F12 => {}
+            _ => return false,
+        }
+
+        true
+    }
 }
 
 impl ApplicationHandler for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        self.state = Some(WindowState::new(event_loop));
+        self.state = Some(WindowState::new(event_loop, &self.title));
     }
 
     fn window_event(
@@ -49,6 +333,7 @@ impl ApplicationHandler for App {
                     KeyEvent {
                         physical_key: PhysicalKey::Code(key),
                         state,
+                        repeat,
                         ..
                     },
                 ..
@@ -57,7 +342,14 @@ impl ApplicationHandler for App {
                     event_loop.exit();
                     return;
                 }
-                if let Some(chip8_key) = map_keycode(key) {
+                if self.handle_hotkey(key, state == ElementState::Pressed && !repeat) {
+                    return;
+                }
+                if self.player.is_some() {
+                    return;
+                }
+                let chip8_key = map_keycode(key).or_else(|| map_game_keycode(key, &self.keys));
+                if let Some(chip8_key) = chip8_key {
                     match state {
                         ElementState::Pressed => self.emulator.keypad.key_down(chip8_key),
                         ElementState::Released => self.emulator.keypad.key_up(chip8_key),
@@ -66,7 +358,7 @@ impl ApplicationHandler for App {
             }
             WindowEvent::RedrawRequested => {
                 if let Some(state) = &mut self.state {
-                    state.render(self.emulator.display.get_buffer());
+                    state.render(self.emulator.display.get_buffer(), &self.palette);
                 }
             }
             _ => {}
@@ -80,23 +372,64 @@ impl ApplicationHandler for App {
 
         if now >= next_tick {
             self.last_tick = now;
+            let mut events = FrameEvents::default();
 
-            for _ in 0..10 {
-                self.emulator.execute_instruction_cycle();
+            if let Some(player) = &mut self.player {
+                if !player.play_frame(&mut self.emulator.keypad) {
+                    println!("Movie finished, keyboard input enabled");
+                    self.player = None;
+                    self.emulator.keypad.clear();
+                }
+            }
+            if let Some((recorder, _)) = &mut self.recorder {
+                recorder.record_frame(&self.emulator.keypad);
+            }
+            if self.cheats_enabled {
+                self.cheats.apply(&mut self.emulator);
             }
-            self.emulator.decrement_timers();
 
-            if self.emulator.is_sound_playing() {
-                self.audio.play();
-            } else {
-                self.audio.pause();
+            for _ in 0..self.cycles_per_frame {
+                if self.emulator.is_idle() {
+                    break;
+                }
+                self.emulator.execute_instruction_cycle_with(&mut events);
             }
+            self.emulator.decrement_timers_with(&mut events);
 
-            if let Some(state) = &self.state {
-                state.window.request_redraw();
+            self.samples.resize(self.synth.next_frame_len(), 0.0);
+            self.synth
+                .render_frame(self.emulator.last_frame_sound(), &mut self.samples);
+            self.audio.push_samples(&self.samples);
+
+            if let Some((recorder, path)) = &mut self.wav {
+                if let Err(err) = recorder.record_frame(self.emulator.last_frame_sound()) {
+                    eprintln!("Could not write audio {}: {err}", path.display());
+                    self.wav = None;
+                }
+            }
+            if let Some((recorder, path)) = &mut self.gif {
+                if let Err(err) = recorder.record_frame(&self.emulator.display) {
+                    eprintln!("Could not write animation {}: {err}", path.display());
+                    self.gif = None;
+                }
+            }
+
+            if let Some(flags) = events.saved_flags.filter(|_| !self.movie_mode) {
+                self.save_flags(&flags);
+            }
+            if events.redraw {
+                if let Some(state) = &self.state {
+                    state.window.request_redraw();
+                }
             }
         }
 
         event_loop.set_control_flow(ControlFlow::WaitUntil(self.last_tick + frame_duration));
     }
+
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        self.save_movie();
+        self.stop_wav();
+        self.stop_gif();
+    }
 }
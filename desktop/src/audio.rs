@@ -1,11 +1,16 @@
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{Stream, StreamConfig};
-use std::f32::consts::PI;
+use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
 
+/// Keeps at most this much audio queued so a stalled frame can't build up
+/// latency; older samples are dropped first.
+const MAX_QUEUED_SECONDS: f32 = 0.1;
+
 pub struct AudioDevice {
     _stream: Stream,
-    playing: Arc<Mutex<bool>>,
+    sample_rate: u32,
+    queue: Arc<Mutex<VecDeque<f32>>>,
 }
 
 impl AudioDevice {
@@ -17,30 +22,20 @@ impl AudioDevice {
         let config = device
             .default_output_config()
             .expect("default output config");
-        let sample_rate = config.sample_rate().0 as f32;
+        let sample_rate = config.sample_rate().0;
         let channels = config.channels() as usize;
 
-        let playing = Arc::new(Mutex::new(false));
-        let playing_clone = playing.clone();
-
-        let mut phase: f32 = 0.0;
-        let phase_inc = 440.0 / sample_rate;
+        let queue = Arc::new(Mutex::new(VecDeque::new()));
+        let queue_clone = queue.clone();
 
         let stream_config: StreamConfig = config.into();
         let stream = device
             .build_output_stream(
                 &stream_config,
                 move |data: &mut [f32], _| {
-                    let is_playing = *playing_clone.lock().unwrap();
+                    let mut queue = queue_clone.lock().unwrap();
                     for frame in data.chunks_mut(channels) {
-                        let sample = if is_playing {
-                            0.25 * (2.0 * PI * phase).sin()
-                        } else {
-                            0.0
-                        };
-                        if is_playing {
-                            phase = (phase + phase_inc) % 1.0;
-                        }
+                        let sample = queue.pop_front().unwrap_or(0.0);
                         for s in frame.iter_mut() {
                             *s = sample;
                         }
@@ -55,15 +50,21 @@ impl AudioDevice {
 
         Self {
             _stream: stream,
-            playing,
+            sample_rate,
+            queue,
         }
     }
 
-    pub fn play(&mut self) {
-        *self.playing.lock().unwrap() = true;
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
     }
 
-    pub fn pause(&mut self) {
-        *self.playing.lock().unwrap() = false;
+    pub fn push_samples(&mut self, samples: &[f32]) {
+        let max_len = (self.sample_rate as f32 * MAX_QUEUED_SECONDS) as usize;
+
+        let mut queue = self.queue.lock().unwrap();
+        queue.extend(samples);
+        let excess = queue.len().saturating_sub(max_len);
+        queue.drain(..excess);
     }
 }
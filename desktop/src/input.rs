@@ -1,3 +1,4 @@
+use chip8_emulator::KeyMapping;
 use winit::keyboard::KeyCode;
 
 pub fn map_keycode(key: KeyCode) -> Option<u8> {
@@ -21,3 +22,17 @@ pub fn map_keycode(key: KeyCode) -> Option<u8> {
         _ => None,
     }
 }
+
+/// Arrow keys, space and enter for games the ROM database knows the
+/// controls of.
+pub fn map_game_keycode(key: KeyCode, keys: &KeyMapping) -> Option<u8> {
+    match key {
+        KeyCode::ArrowUp => keys.up,
+        KeyCode::ArrowDown => keys.down,
+        KeyCode::ArrowLeft => keys.left,
+        KeyCode::ArrowRight => keys.right,
+        KeyCode::Space => keys.a,
+        KeyCode::Enter => keys.b,
+        _ => None,
+    }
+}
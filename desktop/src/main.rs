@@ -4,29 +4,184 @@ mod display;
 mod input;
 mod window;
 
-use std::fs;
-use std::path::Path;
+use chip8_emulator::{AssembleError, Cartridge, Cheats, Movie, Patch, RomInfo};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
 use winit::event_loop::EventLoop;
 
-fn get_rom_file_path() -> String {
-    let args: Vec<_> = std::env::args().collect();
-    if args.len() != 2 {
-        eprintln!("Usage: {} <rom_file_path>", args[0]);
-        std::process::exit(1);
+struct Args {
+    rom_file_path: String,
+    record: Option<PathBuf>,
+    play: Option<PathBuf>,
+    wav: Option<PathBuf>,
+    cheats: Option<PathBuf>,
+    patches: Vec<PathBuf>,
+}
+
+fn exit_with_usage(program: &str) -> ! {
+    eprintln!("Usage: {program} [--record <movie_file>] [--play <movie_file>] [--wav <wav_file>] [--cheats <cheat_file>] [--patch <ips_or_bps_file>]... <rom_file_path>");
+    eprintln!("The rom can also be an Octo cartridge, a .gif with the program and its settings.");
+    std::process::exit(1);
+}
+
+fn parse_args() -> Args {
+    let mut args = std::env::args();
+    let program = args.next().unwrap_or_default();
+
+    let mut rom_file_path = None;
+    let mut record = None;
+    let mut play = None;
+    let mut wav = None;
+    let mut cheats = None;
+    let mut patches = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--record" => {
+                record = Some(
+                    args.next()
+                        .unwrap_or_else(|| exit_with_usage(&program))
+                        .into(),
+                )
+            }
+            "--play" => {
+                play = Some(
+                    args.next()
+                        .unwrap_or_else(|| exit_with_usage(&program))
+                        .into(),
+                )
+            }
+            "--wav" => {
+                wav = Some(
+                    args.next()
+                        .unwrap_or_else(|| exit_with_usage(&program))
+                        .into(),
+                )
+            }
+            "--cheats" => {
+                cheats = Some(
+                    args.next()
+                        .unwrap_or_else(|| exit_with_usage(&program))
+                        .into(),
+                )
+            }
+            "--patch" => patches.push(
+                args.next()
+                    .unwrap_or_else(|| exit_with_usage(&program))
+                    .into(),
+            ),
+            _ if !arg.starts_with("--") && rom_file_path.is_none() => rom_file_path = Some(arg),
+            _ => exit_with_usage(&program),
+        }
     }
-    let path = args[1].clone();
-    if !Path::new(&path).is_file() {
+
+    let Some(rom_file_path) = rom_file_path else {
+        exit_with_usage(&program);
+    };
+    if !Path::new(&rom_file_path).is_file() {
         eprintln!("The rom_file_path should be a valid file.");
         std::process::exit(1);
     }
-    path
+
+    Args {
+        rom_file_path,
+        record,
+        play,
+        wav,
+        cheats,
+        patches,
+    }
+}
+
+fn read_movie(path: &Path, rom: &[u8]) -> Movie {
+    let movie = File::open(path)
+        .and_then(|file| Movie::read_from(BufReader::new(file)))
+        .unwrap_or_else(|err| {
+            eprintln!("Could not read movie {}: {err}", path.display());
+            std::process::exit(1);
+        });
+
+    if !movie.matches(rom) {
+        eprintln!("The movie was recorded with a different rom.");
+        std::process::exit(1);
+    }
+
+    movie
+}
+
+/// Reads `<name>: <register or address> = <value>` lines, like those in
+/// `cheats/`.
+fn read_cheats(path: &Path) -> Cheats {
+    let text = fs::read_to_string(path).unwrap_or_else(|err| {
+        eprintln!("Could not read cheats {}: {err}", path.display());
+        std::process::exit(1);
+    });
+
+    text.parse().unwrap_or_else(|err| {
+        eprintln!("Invalid cheats {}: {err}", path.display());
+        std::process::exit(1);
+    })
+}
+
+/// Reads a raw ROM, or assembles the program of an Octo cartridge and takes
+/// its settings along.
+fn read_rom(path: &Path) -> (Vec<u8>, Option<RomInfo>) {
+    let is_cartridge = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"));
+    if !is_cartridge {
+        return (fs::read(path).expect("open rom file"), None);
+    }
+
+    let cartridge = File::open(path)
+        .and_then(|file| Cartridge::read_from(BufReader::new(file)))
+        .unwrap_or_else(|err| {
+            eprintln!("Could not load cartridge {}: {err}", path.display());
+            let assembly_failed = err
+                .get_ref()
+                .is_some_and(|inner| inner.is::<AssembleError>());
+            if assembly_failed {
+                eprintln!("Programs using :stringmode, :next, :pointer or :assert can't be assembled yet.");
+            }
+            std::process::exit(1);
+        });
+    let title = path
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+    let info = cartridge.rom_info(title);
+    (cartridge.rom, Some(info))
+}
+
+/// Applies an IPS or BPS patch, exiting when it can't be read or is for a
+/// different ROM.
+fn apply_patch(rom: Vec<u8>, path: &Path) -> Vec<u8> {
+    File::open(path)
+        .and_then(|file| Patch::read_from(BufReader::new(file)))
+        .and_then(|patch| patch.apply(&rom))
+        .unwrap_or_else(|err| {
+            eprintln!("Could not apply patch {}: {err}", path.display());
+            std::process::exit(1);
+        })
 }
 
 fn main() {
-    let rom_file_path = get_rom_file_path();
-    let rom = fs::read(rom_file_path).expect("open rom file");
+    let args = parse_args();
+    let (rom, info) = read_rom(Path::new(&args.rom_file_path));
+    let rom = args
+        .patches
+        .iter()
+        .fold(rom, |rom, path| apply_patch(rom, path));
+
+    let options = app::Options {
+        info,
+        record: args.record,
+        movie: args.play.map(|path| read_movie(&path, &rom)),
+        wav: args.wav,
+        cheats: args.cheats.as_deref().map(read_cheats).unwrap_or_default(),
+    };
 
     let event_loop = EventLoop::new().expect("create event loop");
-    let mut app = app::App::new(&rom);
+    let mut app = app::App::new(&rom, options);
     event_loop.run_app(&mut app).expect("run app");
 }
@@ -1,4 +1,5 @@
 use crate::display::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use chip8_emulator::Palette;
 use softbuffer::{Context, Surface};
 use std::num::NonZeroU32;
 use std::sync::Arc;
@@ -12,10 +13,10 @@ pub struct WindowState {
 }
 
 impl WindowState {
-    pub fn new(event_loop: &ActiveEventLoop) -> Self {
+    pub fn new(event_loop: &ActiveEventLoop, title: &str) -> Self {
         let size = LogicalSize::new(WINDOW_WIDTH, WINDOW_HEIGHT);
         let attrs = Window::default_attributes()
-            .with_title("Chip-8 Emulator")
+            .with_title(title)
             .with_inner_size(size)
             .with_resizable(false);
         let window = Arc::new(event_loop.create_window(attrs).expect("create window"));
@@ -32,9 +33,9 @@ impl WindowState {
         Self { window, surface }
     }
 
-    pub fn render(&mut self, chip8_buffer: &[bool; 2048]) {
+    pub fn render(&mut self, chip8_buffer: &[bool; 2048], palette: &Palette) {
         let mut frame = self.surface.buffer_mut().expect("get frame buffer");
-        crate::display::render(&mut frame, chip8_buffer);
+        crate::display::render(&mut frame, chip8_buffer, palette);
         frame.present().expect("present frame");
     }
 }
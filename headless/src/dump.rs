@@ -0,0 +1,42 @@
+use chip8_emulator::{Emulator, Instruction};
+use std::fmt::Write;
+
+const BYTES_PER_LINE: usize = 16;
+
+pub fn registers(emulator: &Emulator) -> String {
+    let mut out = String::new();
+
+    for (x, value) in emulator.v_registers().iter().enumerate() {
+        let _ = writeln!(out, "V{x:X}: {value:02X}");
+    }
+    let pc = emulator.program_counter();
+    let opcode = Instruction::read(emulator.memory(), pc).opcode();
+    let _ = writeln!(out, "I:  {:03X}", emulator.i_register());
+    let _ = writeln!(out, "PC: {pc:03X} ({opcode:04X})");
+    let _ = writeln!(out, "DT: {:02X}", emulator.delay_timer());
+    let _ = writeln!(out, "ST: {:02X}", emulator.sound_timer());
+
+    let stack: Vec<_> = emulator
+        .stack()
+        .map(|address| format!("{address:03X}"))
+        .collect();
+    let _ = writeln!(out, "Stack: [{}]", stack.join(", "));
+
+    out
+}
+
+/// A classic hex dump of all of memory, 16 bytes per line.
+pub fn memory(emulator: &Emulator) -> String {
+    let memory = emulator.memory().as_slice();
+    let mut out = String::new();
+
+    for (line, bytes) in memory.chunks(BYTES_PER_LINE).enumerate() {
+        let _ = write!(out, "{:03X}:", line * BYTES_PER_LINE);
+        for byte in bytes {
+            let _ = write!(out, " {byte:02X}");
+        }
+        out.push('\n');
+    }
+
+    out
+}
@@ -0,0 +1,458 @@
+mod dump;
+mod script;
+
+use chip8_emulator::{
+    guess_platform, Analysis, Cheats, Coverage, Emulator, GifRecorder, Keypad, Movie, MoviePlayer,
+    Palette, Profiler, QuirkDetector, Quirks, WavRecorder,
+};
+use script::Script;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read};
+use std::path::{Path, PathBuf};
+
+const CYCLES_PER_FRAME: u32 = 10;
+const DEFAULT_FRAMES: u32 = 600;
+const DEFAULT_SCALE: u32 = 10;
+
+/// Exit status when `--until` wasn't met within `--frames`.
+const EXIT_CONDITION_NOT_MET: i32 = 2;
+
+enum Until {
+    Idle,
+    ProgramCounter(u16),
+}
+
+enum Dump {
+    Screen,
+    Registers,
+    Memory,
+}
+
+enum Input {
+    None,
+    Script(Script),
+    Movie(MoviePlayer),
+}
+
+impl Input {
+    fn apply(&mut self, frame: u32, keypad: &mut Keypad) {
+        match self {
+            Input::None => {}
+            Input::Script(script) => script.apply(frame, keypad),
+            Input::Movie(player) => {
+                player.play_frame(keypad);
+            }
+        }
+    }
+}
+
+struct Args {
+    rom_file_path: PathBuf,
+    frames: u32,
+    cycles: u32,
+    seed: u64,
+    quirks: Quirks,
+    detect_quirks: bool,
+    until: Option<Until>,
+    script: Option<PathBuf>,
+    movie: Option<PathBuf>,
+    cheats: Option<PathBuf>,
+    dumps: Vec<Dump>,
+    wav: Option<PathBuf>,
+    png: Option<PathBuf>,
+    gif: Option<PathBuf>,
+    scale: u32,
+    profile: Option<PathBuf>,
+    folded: Option<PathBuf>,
+    coverage: Option<PathBuf>,
+    listing: Option<PathBuf>,
+    dot: Option<PathBuf>,
+}
+
+fn exit_with_usage(program: &str) -> ! {
+    eprintln!("Usage: {program} [options] <rom_file_path>");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  --frames <count>     frames to run at most, {DEFAULT_FRAMES} by default");
+    eprintln!("  --cycles <count>     instructions per frame, {CYCLES_PER_FRAME} by default");
+    eprintln!("  --seed <seed>        seed for the random number generator");
+    eprintln!(
+        "  --quirks <profile>   run as `vip`, `schip` or `xo-chip` would, movies bring their own"
+    );
+    eprintln!("  --detect-quirks      run under every quirk profile and compare them");
+    eprintln!("  --until <condition>  stop early on `idle` or `pc=<hex address>`");
+    eprintln!("  --script <file>      keypad input as `<frame> down|up <key>` lines, - for stdin");
+    eprintln!("  --movie <file>       replay a movie recorded with the desktop app");
+    eprintln!("  --cheats <file>      hold values as `<name>: <register or address> = <value>`");
+    eprintln!("  --dump <what>        print `screen`, `registers` or `memory` at the end");
+    eprintln!("  --wav <wav_file>     record the audio");
+    eprintln!("  --png <png_file>     save the final frame");
+    eprintln!("  --gif <gif_file>     record an animation of the run");
+    eprintln!("  --scale <scale>      pixel size of images, {DEFAULT_SCALE} by default");
+    eprintln!("  --profile <file>     write where the cycles went as a table");
+    eprintln!("  --folded <file>      write cycles per call stack for flamegraph tools");
+    eprintln!("  --coverage <file>    write which bytes were executed, read or written");
+    eprintln!("  --listing <file>     write a disassembly of the code found statically and run");
+    eprintln!("  --dot <file>         write the control flow graph for Graphviz");
+    eprintln!();
+    eprintln!("Exits with {EXIT_CONDITION_NOT_MET} if the --until condition wasn't met.");
+    std::process::exit(1);
+}
+
+fn exit_with_error(message: String) -> ! {
+    eprintln!("{message}");
+    std::process::exit(1);
+}
+
+fn parse_until(value: &str) -> Option<Until> {
+    match value {
+        "idle" => Some(Until::Idle),
+        _ => {
+            let address = value.strip_prefix("pc=")?;
+            let address = address.strip_prefix("0x").unwrap_or(address);
+            u16::from_str_radix(address, 16)
+                .ok()
+                .map(Until::ProgramCounter)
+        }
+    }
+}
+
+fn parse_dump(value: &str) -> Option<Dump> {
+    match value {
+        "screen" => Some(Dump::Screen),
+        "registers" => Some(Dump::Registers),
+        "memory" => Some(Dump::Memory),
+        _ => None,
+    }
+}
+
+fn parse_args() -> Args {
+    let mut args = std::env::args();
+    let program = args.next().unwrap_or_default();
+
+    let mut rom_file_path = None;
+    let mut frames = DEFAULT_FRAMES;
+    let mut cycles = CYCLES_PER_FRAME;
+    let mut seed = 0;
+    let mut quirks = Quirks::default();
+    let mut detect_quirks = false;
+    let mut until = None;
+    let mut script = None;
+    let mut movie = None;
+    let mut cheats = None;
+    let mut dumps = Vec::new();
+    let mut wav = None;
+    let mut png = None;
+    let mut gif = None;
+    let mut scale = DEFAULT_SCALE;
+    let mut profile = None;
+    let mut folded = None;
+    let mut coverage = None;
+    let mut listing = None;
+    let mut dot = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| exit_with_usage(&program));
+
+        match arg.as_str() {
+            "--frames" => {
+                frames = value()
+                    .parse()
+                    .unwrap_or_else(|_| exit_with_usage(&program))
+            }
+            "--cycles" => {
+                cycles = value()
+                    .parse()
+                    .unwrap_or_else(|_| exit_with_usage(&program))
+            }
+            "--seed" => {
+                seed = value()
+                    .parse()
+                    .unwrap_or_else(|_| exit_with_usage(&program))
+            }
+            "--quirks" => {
+                quirks = Quirks::profile(&value()).unwrap_or_else(|| exit_with_usage(&program))
+            }
+            "--detect-quirks" => detect_quirks = true,
+            "--until" => {
+                until = Some(parse_until(&value()).unwrap_or_else(|| exit_with_usage(&program)))
+            }
+            "--script" => script = Some(value().into()),
+            "--movie" => movie = Some(value().into()),
+            "--cheats" => cheats = Some(value().into()),
+            "--dump" => {
+                dumps.push(parse_dump(&value()).unwrap_or_else(|| exit_with_usage(&program)))
+            }
+            "--wav" => wav = Some(value().into()),
+            "--png" => png = Some(value().into()),
+            "--gif" => gif = Some(value().into()),
+            "--profile" => profile = Some(value().into()),
+            "--folded" => folded = Some(value().into()),
+            "--coverage" => coverage = Some(value().into()),
+            "--listing" => listing = Some(value().into()),
+            "--dot" => dot = Some(value().into()),
+            "--scale" => {
+                scale = value()
+                    .parse()
+                    .unwrap_or_else(|_| exit_with_usage(&program))
+            }
+            _ if !arg.starts_with("--") && rom_file_path.is_none() => {
+                rom_file_path = Some(arg.into())
+            }
+            _ => exit_with_usage(&program),
+        }
+    }
+
+    let Some(rom_file_path) = rom_file_path else {
+        exit_with_usage(&program);
+    };
+    if script.is_some() && movie.is_some() {
+        exit_with_usage(&program);
+    }
+
+    Args {
+        rom_file_path,
+        frames,
+        cycles,
+        seed,
+        quirks,
+        detect_quirks,
+        until,
+        script,
+        movie,
+        cheats,
+        dumps,
+        wav,
+        png,
+        gif,
+        scale,
+        profile,
+        folded,
+        coverage,
+        listing,
+        dot,
+    }
+}
+
+fn read_script(path: &Path) -> Script {
+    let mut text = String::new();
+    let result = if path.as_os_str() == "-" {
+        io::stdin().read_to_string(&mut text)
+    } else {
+        File::open(path).and_then(|mut file| file.read_to_string(&mut text))
+    };
+    result
+        .unwrap_or_else(|err| exit_with_error(format!("Could not read {}: {err}", path.display())));
+
+    text.parse()
+        .unwrap_or_else(|err| exit_with_error(format!("Invalid script {}: {err}", path.display())))
+}
+
+fn read_cheats(path: &Path) -> Cheats {
+    fs::read_to_string(path)
+        .unwrap_or_else(|err| exit_with_error(format!("Could not read {}: {err}", path.display())))
+        .parse()
+        .unwrap_or_else(|err| exit_with_error(format!("Invalid cheats {}: {err}", path.display())))
+}
+
+fn read_movie(path: &Path, rom: &[u8]) -> Movie {
+    let movie = File::open(path)
+        .and_then(|file| Movie::read_from(BufReader::new(file)))
+        .unwrap_or_else(|err| {
+            exit_with_error(format!("Could not read movie {}: {err}", path.display()))
+        });
+
+    if !movie.matches(rom) {
+        exit_with_error("The movie was recorded with a different rom.".to_owned());
+    }
+
+    movie
+}
+
+fn main() {
+    let args = parse_args();
+    let rom = fs::read(&args.rom_file_path).unwrap_or_else(|err| {
+        exit_with_error(format!(
+            "Could not read {}: {err}",
+            args.rom_file_path.display()
+        ))
+    });
+
+    let (mut input, seed, cycles, quirks) = match (&args.script, &args.movie) {
+        (_, Some(path)) => {
+            let movie = read_movie(path, &rom);
+            let (seed, cycles, quirks) = (movie.seed, movie.cycles_per_frame, movie.quirks);
+            (Input::Movie(MoviePlayer::new(movie)), seed, cycles, quirks)
+        }
+        (Some(path), None) => (
+            Input::Script(read_script(path)),
+            args.seed,
+            args.cycles,
+            args.quirks,
+        ),
+        (None, None) => (Input::None, args.seed, args.cycles, args.quirks),
+    };
+
+    if args.detect_quirks {
+        let report = QuirkDetector::new(args.frames, cycles)
+            .with_seed(seed)
+            .run(&rom, |frame, keypad| input.apply(frame, keypad))
+            .unwrap_or_else(|err| exit_with_error(format!("Could not load rom: {err}")));
+        report
+            .write_report(io::stdout())
+            .unwrap_or_else(|err| exit_with_error(format!("Could not write report: {err}")));
+
+        let guess = guess_platform(&rom);
+        let opcodes: Vec<_> = guess.opcodes().collect();
+        println!("Platform by opcodes: {guess}");
+        if !opcodes.is_empty() {
+            println!("  found {}", opcodes.join(", "));
+        }
+        return;
+    }
+
+    let mut emulator = Emulator::new();
+    emulator.set_rng_seed(seed);
+    emulator.set_quirks(quirks);
+    emulator.reset();
+    emulator
+        .load_rom(&rom)
+        .unwrap_or_else(|err| exit_with_error(format!("Could not load rom: {err}")));
+
+    let mut wav = args.wav.as_ref().map(|path| {
+        File::create(path)
+            .and_then(|file| WavRecorder::new(BufWriter::new(file), cycles))
+            .unwrap_or_else(|err| {
+                exit_with_error(format!("Could not create {}: {err}", path.display()))
+            })
+    });
+
+    let mut gif = args.gif.as_ref().map(|path| {
+        File::create(path)
+            .and_then(|file| GifRecorder::new(BufWriter::new(file), args.scale, Palette::default()))
+            .unwrap_or_else(|err| {
+                exit_with_error(format!("Could not create {}: {err}", path.display()))
+            })
+    });
+
+    let profiler = (args.profile.is_some() || args.folded.is_some()).then(Profiler::new);
+    let coverage = (args.coverage.is_some() || args.listing.is_some() || args.dot.is_some())
+        .then(Coverage::new);
+    let mut observers = (profiler, coverage);
+
+    let cheats = args.cheats.as_deref().map(read_cheats).unwrap_or_default();
+
+    let mut condition_met = false;
+    'frames: for frame in 0..args.frames {
+        input.apply(frame, &mut emulator.keypad);
+        cheats.apply(&mut emulator);
+
+        for _ in 0..cycles {
+            if let Some(Until::ProgramCounter(address)) = args.until {
+                if emulator.program_counter() == address {
+                    condition_met = true;
+                    break 'frames;
+                }
+            }
+            if emulator.is_idle() {
+                break;
+            }
+            emulator.execute_instruction_cycle_with(&mut observers);
+        }
+        emulator.decrement_timers();
+
+        if let Some(wav) = &mut wav {
+            wav.record_frame(emulator.last_frame_sound())
+                .unwrap_or_else(|err| exit_with_error(format!("Could not write audio: {err}")));
+        }
+        if let Some(gif) = &mut gif {
+            gif.record_frame(&emulator.display)
+                .unwrap_or_else(|err| exit_with_error(format!("Could not write animation: {err}")));
+        }
+
+        if matches!(args.until, Some(Until::Idle)) && emulator.is_idle() {
+            condition_met = true;
+            break;
+        }
+    }
+
+    if let (Some(wav), Some(path)) = (wav, &args.wav) {
+        wav.finish().unwrap_or_else(|err| {
+            exit_with_error(format!("Could not save {}: {err}", path.display()))
+        });
+    }
+
+    if let (Some(gif), Some(path)) = (gif, &args.gif) {
+        gif.finish().unwrap_or_else(|err| {
+            exit_with_error(format!("Could not save {}: {err}", path.display()))
+        });
+    }
+
+    if let Some(path) = &args.png {
+        File::create(path)
+            .and_then(|file| {
+                emulator
+                    .display
+                    .write_png(BufWriter::new(file), args.scale, Palette::default())
+            })
+            .unwrap_or_else(|err| {
+                exit_with_error(format!("Could not save {}: {err}", path.display()))
+            });
+    }
+
+    let (profiler, coverage) = &observers;
+
+    if let (Some(profiler), Some(path)) = (profiler, &args.profile) {
+        File::create(path)
+            .and_then(|file| profiler.write_report(BufWriter::new(file)))
+            .unwrap_or_else(|err| {
+                exit_with_error(format!("Could not save {}: {err}", path.display()))
+            });
+    }
+
+    if let (Some(profiler), Some(path)) = (profiler, &args.folded) {
+        File::create(path)
+            .and_then(|file| profiler.write_folded(BufWriter::new(file)))
+            .unwrap_or_else(|err| {
+                exit_with_error(format!("Could not save {}: {err}", path.display()))
+            });
+    }
+
+    if let (Some(coverage), Some(path)) = (coverage, &args.coverage) {
+        fs::write(path, coverage.to_string()).unwrap_or_else(|err| {
+            exit_with_error(format!("Could not save {}: {err}", path.display()))
+        });
+    }
+
+    if args.listing.is_some() || args.dot.is_some() {
+        let analysis = Analysis::with_coverage(&rom, coverage.as_ref().unwrap());
+
+        if let Some(path) = &args.listing {
+            File::create(path)
+                .and_then(|file| analysis.write_listing(BufWriter::new(file)))
+                .unwrap_or_else(|err| {
+                    exit_with_error(format!("Could not save {}: {err}", path.display()))
+                });
+        }
+
+        if let Some(path) = &args.dot {
+            File::create(path)
+                .and_then(|file| analysis.write_dot(BufWriter::new(file)))
+                .unwrap_or_else(|err| {
+                    exit_with_error(format!("Could not save {}: {err}", path.display()))
+                });
+        }
+    }
+
+    for dump in &args.dumps {
+        match dump {
+            Dump::Screen => print!("{}", emulator.display),
+            Dump::Registers => print!("{}", dump::registers(&emulator)),
+            Dump::Memory => print!("{}", dump::memory(&emulator)),
+        }
+    }
+
+    if args.until.is_some() && !condition_met {
+        std::process::exit(EXIT_CONDITION_NOT_MET);
+    }
+}
@@ -0,0 +1,114 @@
+use chip8_emulator::Keypad;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct KeyEvent {
+    frame: u32,
+    key: u8,
+    pressed: bool,
+}
+
+/// Scripted keypad input, one `<frame> down|up <key>` line per event, e.g.
+///
+/// ```text
+/// # start the game
+/// 60 down 5
+/// 64 up 5
+/// ```
+///
+/// Keys are single hex digits. Events apply at the start of their frame,
+/// before any instruction runs.
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    events: Vec<KeyEvent>,
+    next: usize,
+}
+
+impl Script {
+    pub fn apply(&mut self, frame: u32, keypad: &mut Keypad) {
+        while let Some(event) = self.events.get(self.next) {
+            if event.frame > frame {
+                break;
+            }
+            if event.pressed {
+                keypad.key_down(event.key);
+            } else {
+                keypad.key_up(event.key);
+            }
+            self.next += 1;
+        }
+    }
+}
+
+impl FromStr for Script {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut events = Vec::new();
+
+        for (number, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let invalid = |message: &str| format!("line {}: {message}", number + 1);
+            let mut fields = line.split_whitespace();
+            let (Some(frame), Some(action), Some(key), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return Err(invalid("expected `<frame> down|up <key>`"));
+            };
+
+            let frame = frame.parse().map_err(|_| invalid("invalid frame"))?;
+            let pressed = match action {
+                "down" => true,
+                "up" => false,
+                _ => return Err(invalid("expected `down` or `up`")),
+            };
+            let key = match u8::from_str_radix(key, 16) {
+                Ok(key) if key <= 0xF => key,
+                _ => return Err(invalid("key should be a hex digit")),
+            };
+
+            events.push(KeyEvent {
+                frame,
+                key,
+                pressed,
+            });
+        }
+        events.sort_by_key(|event| event.frame);
+
+        Ok(Self { events, next: 0 })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_events_in_frame_order() {
+        let mut script: Script = "# comment\n10 up 5\n\n2 down 5 # press\n2 down a\n"
+            .parse()
+            .unwrap();
+        let mut keypad = Keypad::new();
+
+        script.apply(1, &mut keypad);
+        assert_eq!(keypad.state(), 0);
+        script.apply(2, &mut keypad);
+        assert_eq!(keypad.state(), 1 << 5 | 1 << 0xA);
+        script.apply(10, &mut keypad);
+        assert_eq!(keypad.state(), 1 << 0xA);
+    }
+
+    #[test]
+    fn rejects_bad_lines() {
+        assert_eq!(
+            "1 down 5\n2 hold 5".parse::<Script>().unwrap_err(),
+            "line 2: expected `down` or `up`"
+        );
+        assert!("1 down 10".parse::<Script>().is_err());
+        assert!("down 5".parse::<Script>().is_err());
+    }
+}
@@ -2,7 +2,9 @@
 
 mod utils;
 
-use js_sys::Array;
+use chip8_emulator::{guess_platform, AudioSynth, Observer, Quirks, RomDatabase, RomHash, RomInfo};
+use js_sys::{Array, Float32Array, Function, Math, Uint8Array};
+use std::convert::TryInto;
 use utils::set_panic_hook;
 use wasm_bindgen::prelude::*;
 
@@ -11,22 +13,110 @@ pub fn main() {
     set_panic_hook();
 }
 
+/// Hands the RPL flags saved by `Fx75` to JS.
+struct SaveFlags<'a>(Option<&'a Function>);
+
+impl Observer for SaveFlags<'_> {
+    fn on_save_flags(&mut self, flags: &[u8; 16]) {
+        if let Some(callback) = self.0 {
+            // Losing the flags isn't worth stopping the game over.
+            let _ = callback.call1(&JsValue::NULL, &Uint8Array::from(&flags[..]));
+        }
+    }
+}
+
 #[wasm_bindgen]
 pub struct Emulator {
     emulator: chip8_emulator::Emulator,
+    synth: Option<AudioSynth>,
+    info: Option<RomInfo>,
+    rom_hash: Option<RomHash>,
+    flags_callback: Option<Function>,
 }
 
 #[wasm_bindgen]
 impl Emulator {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
+        let mut emulator = chip8_emulator::Emulator::new();
+        emulator.set_rng_seed((Math::random() * u64::MAX as f64) as u64);
+
         Emulator {
-            emulator: chip8_emulator::Emulator::new(),
+            emulator,
+            synth: None,
+            info: None,
+            rom_hash: None,
+            flags_callback: None,
+        }
+    }
+
+    /// Loads `rom` and applies its quirks from the ROM database, or from a
+    /// guess at its platform for ROMs the database doesn't know. ROMs neither
+    /// can place run without quirks. Throws when the ROM doesn't fit in memory.
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), JsError> {
+        let rom_hash = RomHash::of(rom);
+        self.rom_hash = Some(rom_hash);
+        self.info = RomDatabase::builtin().lookup(&rom_hash);
+        self.emulator.set_quirks(
+            self.info
+                .as_ref()
+                .and_then(|info| info.quirks)
+                .or_else(|| guess_platform(rom).quirks())
+                .unwrap_or(Quirks::NONE),
+        );
+        self.emulator
+            .load_rom(rom)
+            .map_err(|err| JsError::new(&err.to_string()))
+    }
+
+    /// The SHA-1 of the loaded ROM in hex, to key what's stored for it.
+    pub fn rom_hash(&self) -> Option<String> {
+        self.rom_hash.map(|hash| hash.to_string())
+    }
+
+    /// Calls `callback` with a `Uint8Array` of the 16 RPL flags whenever the
+    /// program saves them, for them to be restored with `set_rpl_flags`.
+    pub fn set_flags_callback(&mut self, callback: Function) {
+        self.flags_callback = Some(callback);
+    }
+
+    /// Restores RPL flags saved in an earlier session. Anything but 16 bytes
+    /// is ignored.
+    pub fn set_rpl_flags(&mut self, flags: &[u8]) {
+        if let Ok(flags) = flags.try_into() {
+            self.emulator.set_rpl_flags(flags);
         }
     }
 
-    pub fn load_rom(&mut self, rom: &[u8]) {
-        self.emulator.load_rom(rom);
+    /// The loaded game's title from the ROM database.
+    pub fn title(&self) -> Option<String> {
+        self.info.as_ref().map(|info| info.title.clone())
+    }
+
+    /// Instruction cycles per frame the loaded game wants.
+    pub fn tickrate(&self) -> Option<u32> {
+        self.info.as_ref().and_then(|info| info.tickrate)
+    }
+
+    /// The loaded game's colors as `[off r, g, b, on r, g, b]`.
+    pub fn palette(&self) -> Option<Vec<u8>> {
+        let palette = self.info.as_ref()?.palette?;
+        Some([palette.off, palette.on].concat())
+    }
+
+    /// The CHIP-8 key the loaded game uses for `button`, one of `up`,
+    /// `down`, `left`, `right`, `a` and `b`.
+    pub fn game_key(&self, button: &str) -> Option<u8> {
+        let keys = &self.info.as_ref()?.keys;
+        match button {
+            "up" => keys.up,
+            "down" => keys.down,
+            "left" => keys.left,
+            "right" => keys.right,
+            "a" => keys.a,
+            "b" => keys.b,
+            _ => None,
+        }
     }
 
     pub fn reset(&mut self) {
@@ -34,7 +124,12 @@ impl Emulator {
     }
 
     pub fn execute_instruction_cycle(&mut self) {
-        self.emulator.execute_instruction_cycle();
+        self.emulator
+            .execute_instruction_cycle_with(&mut SaveFlags(self.flags_callback.as_ref()));
+    }
+
+    pub fn is_idle(&mut self) -> bool {
+        self.emulator.is_idle()
     }
 
     pub fn decrement_timers(&mut self) {
@@ -57,6 +152,23 @@ impl Emulator {
         JsValue::from_bool(self.emulator.is_sound_playing())
     }
 
+    /// Sets up `render_audio` for an `AudioContext` running at `sample_rate`.
+    pub fn init_audio(&mut self, sample_rate: u32, cycles_per_frame: u32) {
+        self.synth = Some(AudioSynth::new(sample_rate, cycles_per_frame));
+    }
+
+    /// The samples of the frame that ended with the last `decrement_timers`.
+    pub fn render_audio(&mut self) -> Float32Array {
+        let Some(synth) = &mut self.synth else {
+            return Float32Array::new_with_length(0);
+        };
+
+        let mut samples = vec![0.0; synth.next_frame_len()];
+        synth.render_frame(self.emulator.last_frame_sound(), &mut samples);
+
+        Float32Array::from(&samples[..])
+    }
+
     pub fn set_key_down(&mut self, key: u8) {
         self.emulator.keypad.key_down(key);
     }
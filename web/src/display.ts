@@ -1,5 +1,6 @@
 export class Display {
   ctx: CanvasRenderingContext2D;
+  palette: Uint8Array | undefined;
 
   constructor() {
     const canvas = document.querySelector("#display") as HTMLCanvasElement;
@@ -12,14 +13,27 @@ export class Display {
     this.ctx = ctx;
   }
 
+  // `[off r, g, b, on r, g, b]`, or white on the page background.
+  setPalette(palette: Uint8Array | undefined) {
+    this.palette = palette;
+  }
+
   render(buffer: Array<boolean>) {
     const imageData = this.ctx.createImageData(64, 32);
 
     for (let i = 0; i < buffer.length; i++) {
-      imageData.data[i * 4] = 0xff;
-      imageData.data[i * 4 + 1] = 0xff;
-      imageData.data[i * 4 + 2] = 0xff;
-      imageData.data[i * 4 + 3] = buffer[i] ? 0xff : 0x00;
+      if (this.palette) {
+        const color = buffer[i] ? 3 : 0;
+        imageData.data[i * 4] = this.palette[color];
+        imageData.data[i * 4 + 1] = this.palette[color + 1];
+        imageData.data[i * 4 + 2] = this.palette[color + 2];
+        imageData.data[i * 4 + 3] = 0xff;
+      } else {
+        imageData.data[i * 4] = 0xff;
+        imageData.data[i * 4 + 1] = 0xff;
+        imageData.data[i * 4 + 2] = 0xff;
+        imageData.data[i * 4 + 3] = buffer[i] ? 0xff : 0x00;
+      }
     }
 
     this.ctx.putImageData(imageData, 0, 0);
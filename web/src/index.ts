@@ -5,12 +5,16 @@ import { Keypad } from "./keypad";
 import { Display } from "./display";
 import { RomSelector } from "./rom";
 
+const CYCLES_PER_FRAME = 10;
+const FLAGS_KEY_PREFIX = "chip8-flags-";
+
 class Emulator {
   inner: emulator.Emulator;
   keypad: Keypad;
   display: Display;
   soundPlayer: SoundPlayer;
   romSelector: RomSelector;
+  cyclesPerFrame: number;
 
   constructor() {
     this.inner = new emulator.Emulator();
@@ -21,6 +25,8 @@ class Emulator {
     this.display = new Display();
     this.soundPlayer = new SoundPlayer();
     this.romSelector = new RomSelector();
+    this.cyclesPerFrame = CYCLES_PER_FRAME;
+    this.inner.set_flags_callback((flags: Uint8Array) => this.saveFlags(flags));
   }
 
   async initialize() {
@@ -30,27 +36,40 @@ class Emulator {
   startEmulator(rom: Uint8Array) {
     this.inner.reset();
     this.inner.load_rom(rom);
+    this.loadFlags();
+    this.cyclesPerFrame = this.inner.tickrate() ?? CYCLES_PER_FRAME;
+    this.inner.init_audio(this.soundPlayer.initialize(), this.cyclesPerFrame);
+    this.display.setPalette(this.inner.palette());
+    this.keypad.setGameKeys((button: string) => this.inner.game_key(button));
 
     this.keypad.addListeners();
 
     window.requestAnimationFrame(this.gameLoop.bind(this));
   }
 
+  // SCHIP games keep high scores in the RPL flags, which outlived the game on the HP-48.
+  saveFlags(flags: Uint8Array) {
+    const hash = this.inner.rom_hash();
+    if (hash) localStorage.setItem(FLAGS_KEY_PREFIX + hash, JSON.stringify(Array.from(flags)));
+  }
+
+  loadFlags() {
+    const saved = localStorage.getItem(FLAGS_KEY_PREFIX + this.inner.rom_hash());
+    this.inner.set_rpl_flags(new Uint8Array(saved ? JSON.parse(saved) : 16));
+  }
+
   stopEmulator() {
     this.keypad.removeListeners();
     this.inner.reset();
   }
 
   gameLoop() {
-    for (let i = 0; i < 10; i++) this.inner.execute_instruction_cycle();
+    for (let i = 0; i < this.cyclesPerFrame && !this.inner.is_idle(); i++) this.inner.execute_instruction_cycle();
     this.display.render(this.inner.get_display_buffer());
     this.inner.decrement_timers();
-
-    if (this.inner.is_sound_playing()) this.soundPlayer.playTone();
-    else this.soundPlayer.stopTone();
+    this.soundPlayer.queueSamples(this.inner.render_audio());
 
     if (this.romSelector.romLoaded) window.requestAnimationFrame(this.gameLoop.bind(this));
-    else this.soundPlayer.stopTone();
   }
 }
 
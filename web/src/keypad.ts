@@ -1,6 +1,7 @@
 export class Keypad {
   onKeyDown: (key: number) => void;
   onKeyUp: (key: number) => void;
+  gameKeys: { [key: string]: number };
 
   static keyMap = {
     1: 0x1,
@@ -21,9 +22,38 @@ export class Keypad {
     v: 0xf,
   };
 
+  // Buttons the ROM database can assign a CHIP-8 key to.
+  static gameButtons = {
+    arrowup: "up",
+    arrowdown: "down",
+    arrowleft: "left",
+    arrowright: "right",
+    " ": "a",
+    enter: "b",
+  };
+
   constructor(onKeyDown, onKeyUp) {
     this.onKeyDown = onKeyDown;
     this.onKeyUp = onKeyUp;
+    this.gameKeys = {};
+  }
+
+  setGameKeys(gameKey: (button: string) => number | undefined) {
+    this.gameKeys = {};
+    for (const [key, button] of Object.entries(Keypad.gameButtons)) {
+      const chip8Key = gameKey(button);
+      if (chip8Key !== undefined) this.gameKeys[key] = chip8Key;
+    }
+  }
+
+  mapKey(event: KeyboardEvent): number | undefined {
+    const key = event.key.toLowerCase();
+    if (key in this.gameKeys) {
+      // Keep the arrow keys and space from scrolling the page.
+      event.preventDefault();
+      return this.gameKeys[key];
+    }
+    return Keypad.keyMap[key];
   }
 
   addListeners = () => {
@@ -37,14 +67,14 @@ export class Keypad {
   };
 
   keyDownEventHandler = (event: KeyboardEvent) => {
-    const key = Keypad.keyMap[event.key.toLowerCase()];
+    const key = this.mapKey(event);
     if (key !== undefined) {
       this.onKeyDown(key);
     }
   };
 
   keyUpEventHandler = (event: KeyboardEvent) => {
-    const key = Keypad.keyMap[event.key.toLowerCase()];
+    const key = this.mapKey(event);
     if (key !== undefined) {
       this.onKeyUp(key);
     }
@@ -1,36 +1,42 @@
+// Queues the samples the emulator renders each frame back to back, slightly
+// ahead of the audio clock so short gaps in the game loop don't click.
+const LEAD_TIME = 0.05;
+
 export class SoundPlayer {
   isInitialized: boolean;
 
   ctx: AudioContext;
-  oscillator: OscillatorNode;
-  isPlaying: boolean;
+  nextStartTime: number;
 
   constructor() {
     this.isInitialized = false;
   }
 
-  initialize() {
-    this.isInitialized = true;
-    this.ctx = new AudioContext();
-    this.oscillator = this.ctx.createOscillator();
-    this.isPlaying = false;
+  initialize(): number {
+    if (!this.isInitialized) {
+      this.isInitialized = true;
+      this.ctx = new AudioContext();
+      this.nextStartTime = 0;
+    }
 
-    this.oscillator.frequency.value = 440;
-    this.oscillator.start();
+    return this.ctx.sampleRate;
   }
 
-  playTone() {
-    if (!this.isInitialized) this.initialize();
-    if (this.isPlaying) return;
+  queueSamples(samples: Float32Array) {
+    if (!this.isInitialized || samples.length === 0) return;
 
-    this.oscillator.connect(this.ctx.destination);
-    this.isPlaying = true;
-  }
+    const buffer = this.ctx.createBuffer(1, samples.length, this.ctx.sampleRate);
+    buffer.copyToChannel(samples, 0);
 
-  stopTone() {
-    if (!this.isInitialized || !this.isPlaying) return;
+    const source = this.ctx.createBufferSource();
+    source.buffer = buffer;
+    source.connect(this.ctx.destination);
 
-    this.oscillator.disconnect(this.ctx.destination);
-    this.isPlaying = false;
+    const now = this.ctx.currentTime;
+    if (this.nextStartTime < now || this.nextStartTime > now + 2 * LEAD_TIME) {
+      this.nextStartTime = now + LEAD_TIME;
+    }
+    source.start(this.nextStartTime);
+    this.nextStartTime += buffer.duration;
   }
 }